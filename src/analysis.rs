@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::{AnalysisResult, Idea};
+
+/// Common English words that carry no topical signal.
+const STOPWORDS: &[&str] = &[
+    "a", "about", "across", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "but", "by", "can", "cannot", "do", "does", "each", "for", "from", "get",
+    "has", "have", "how", "if", "in", "into", "is", "it", "its", "just", "like", "lot", "many",
+    "more", "most", "much", "need", "needs", "no", "not", "of", "often", "on", "or", "other",
+    "out", "over", "own", "so", "some", "such", "than", "that", "the", "their", "them", "then",
+    "there", "these", "they", "this", "those", "to", "too", "up", "use", "very", "want", "was",
+    "way", "were", "what", "when", "where", "which", "while", "who", "why", "will", "with",
+    "without", "would", "you", "your",
+];
+
+/// Count recurring keywords in `core_problem` and `target_user` across all ideas.
+/// Returns up to `top_n` (keyword, frequency) pairs, most frequent first.
+pub fn top_keywords(ideas: &[Idea], top_n: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for idea in ideas {
+        for text in [&idea.core_problem, &idea.target_user] {
+            for token in text.split(|c: char| !c.is_alphanumeric() && c != '-') {
+                let word = token.trim_matches('-').to_lowercase();
+                if word.chars().count() < 3 || STOPWORDS.contains(&word.as_str()) {
+                    continue;
+                }
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    // Highest count first; ties broken alphabetically for stable output
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top_n);
+    ranked
+}
+
+/// Pick the sentence of `text` with the most topical words, for quoting a long comment.
+/// Ties go to the earlier sentence; text with no sentence breaks is returned whole.
+pub fn top_sentence(text: &str) -> &str {
+    text.split_inclusive(['.', '!', '?', '\n'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .fold(("", 0), |best, sentence| {
+            let weight = sentence
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| w.chars().count() >= 3 && !STOPWORDS.contains(&w.to_lowercase().as_str()))
+                .count();
+            if weight > best.1 || best.0.is_empty() {
+                (sentence, weight)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+/// Lowercase and keep only alphanumerics, so "Invoice-Bot" and "invoice bot" compare equal.
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Two ideas are the same if their normalized product names or core problems match.
+/// Returns which field matched: `product_name` or `core_problem`.
+pub fn matched_field(a: &Idea, b: &Idea) -> Option<&'static str> {
+    if normalize(&a.product_name) == normalize(&b.product_name) {
+        Some("product_name")
+    } else if normalize(&a.core_problem) == normalize(&b.core_problem) {
+        Some("core_problem")
+    } else {
+        None
+    }
+}
+
+/// A duplicate idea that was dropped in favor of an earlier one, for `--dedup-report`.
+#[derive(Serialize, Clone)]
+pub struct Merge {
+    /// Where it was caught: `within-post`, `top-up`, `previous-run` (the diff command), or `merge`.
+    pub stage: &'static str,
+    pub matched_on: &'static str,
+    pub kept: MergedIdea,
+    pub duplicate: MergedIdea,
+}
+
+/// Enough of an idea to find it again in the output or a saved run.
+#[derive(Serialize, Clone)]
+pub struct MergedIdea {
+    pub id: String,
+    pub product_name: String,
+    pub post_url: String,
+}
+
+impl MergedIdea {
+    fn new(idea: &Idea, post_url: &str) -> Self {
+        MergedIdea {
+            id: idea.id.clone(),
+            product_name: idea.product_name.clone(),
+            post_url: post_url.to_string(),
+        }
+    }
+}
+
+/// If `idea` repeats one of `kept`, the merge that folds it into the first match.
+pub fn find_merge<'a>(
+    kept: impl IntoIterator<Item = (&'a Idea, &'a str)>,
+    idea: &Idea,
+    post_url: &str,
+    stage: &'static str,
+) -> Option<Merge> {
+    kept.into_iter().find_map(|(k, kept_url)| {
+        matched_field(k, idea).map(|matched_on| Merge {
+            stage,
+            matched_on,
+            kept: MergedIdea::new(k, kept_url),
+            duplicate: MergedIdea::new(idea, post_url),
+        })
+    })
+}
+
+/// Drop ideas that repeat an earlier idea in the same post (see [`matched_field`]).
+/// Returns the kept ideas, in their original order, and what was merged.
+pub fn dedupe_ideas(ideas: Vec<Idea>, post_url: &str) -> (Vec<Idea>, Vec<Merge>) {
+    let mut kept: Vec<Idea> = Vec::with_capacity(ideas.len());
+    let mut merges = Vec::new();
+    for idea in ideas {
+        let earlier = kept.iter().map(|k| (k, post_url));
+        match find_merge(earlier, &idea, post_url, "within-post") {
+            Some(merge) => merges.push(merge),
+            None => kept.push(idea),
+        }
+    }
+    (kept, merges)
+}
+
+/// Drop ideas that repeat any earlier idea across `results`, in order, so the first
+/// occurrence is kept. Posts left with no ideas are dropped. Returns what was merged.
+pub fn dedupe_runs(results: Vec<AnalysisResult>) -> (Vec<AnalysisResult>, Vec<Merge>) {
+    let mut kept: Vec<AnalysisResult> = Vec::with_capacity(results.len());
+    let mut merges = Vec::new();
+
+    for mut result in results {
+        let mut ideas = Vec::with_capacity(result.ideas.len());
+        for idea in std::mem::take(&mut result.ideas) {
+            let earlier = kept
+                .iter()
+                .flat_map(|r| r.ideas.iter().map(|i| (i, r.url.as_str())))
+                .chain(ideas.iter().map(|i| (i, result.url.as_str())));
+            match find_merge(earlier, &idea, &result.url, "merge") {
+                Some(merge) => merges.push(merge),
+                None => ideas.push(idea),
+            }
+        }
+        result.ideas = ideas;
+        if !result.ideas.is_empty() {
+            kept.push(result);
+        }
+    }
+
+    (kept, merges)
+}
+
+/// Keep only ideas in `new` that have no match anywhere in `old`.
+/// Posts left with no new ideas are dropped. Returns the filtered results and the repeats merged.
+pub fn diff_runs(old: &[AnalysisResult], new: Vec<AnalysisResult>) -> (Vec<AnalysisResult>, Vec<Merge>) {
+    let old_ideas: Vec<(&Idea, &str)> = old
+        .iter()
+        .flat_map(|r| r.ideas.iter().map(|idea| (idea, r.url.as_str())))
+        .collect();
+    let mut merges = Vec::new();
+
+    let fresh = new
+        .into_iter()
+        .filter_map(|mut result| {
+            let url = result.url.clone();
+            result.ideas.retain(|idea| {
+                match find_merge(old_ideas.iter().copied(), idea, &url, "previous-run") {
+                    Some(merge) => {
+                        merges.push(merge);
+                        false
+                    }
+                    None => true,
+                }
+            });
+            (!result.ideas.is_empty()).then_some(result)
+        })
+        .collect();
+
+    (fresh, merges)
+}
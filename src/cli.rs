@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(name = "reddit-research-engine")]
@@ -6,19 +7,262 @@ use clap::{Parser, Subcommand, ValueEnum};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+/// Options shared by every command.
+#[derive(Args, Clone)]
+pub struct GlobalArgs {
+    /// Gemini API key (overrides GEMINI_API_KEY; prefer the env var where possible, as flags can show up in process lists)
+    #[arg(long, global = true)]
+    pub gemini_key: Option<String>,
+
+    /// Google Sheet to export to (overrides GOOGLE_SHEET_ID)
+    #[arg(long, global = true)]
+    pub sheet_id: Option<String>,
+
+    /// Path to the Google service account JSON (overrides GOOGLE_APPLICATION_CREDENTIALS)
+    #[arg(long, global = true)]
+    pub credentials: Option<String>,
+
+    /// Ideas per post filed as GitHub issues when GITHUB_TOKEN and GITHUB_REPO are set
+    #[arg(long, global = true, default_value_t = 1)]
+    pub github_top_n: usize,
+
+    /// Ideas per post created as Trello cards when TRELLO_KEY, TRELLO_TOKEN, and TRELLO_LIST_ID are set
+    #[arg(long, global = true, default_value_t = 1)]
+    pub trello_top_n: usize,
+
+    /// Include each post's body and comments in JSON output alongside its ideas
+    #[arg(long, global = true)]
+    pub include_source: bool,
+
+    /// Include the exact prompt sent to Gemini in JSON output, for auditing
+    #[arg(long, global = true)]
+    pub include_prompt: bool,
+
+    /// Cut idea fields longer than this many characters with an ellipsis in text and markdown output
+    #[arg(long, global = true, value_name = "N")]
+    pub truncate_fields: Option<usize>,
+
+    /// Time each stage (Reddit fetch, Gemini, parsing, exports) per post and print a breakdown at the end
+    #[arg(long, global = true)]
+    pub profile: bool,
+
+    /// Add to existing --save files instead of overwriting them: JSON runs are merged, CSV gains rows
+    #[arg(long, global = true)]
+    pub save_append: bool,
+
+    /// Write which ideas were dropped as duplicates, and which idea each was folded into, to this JSON file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub dedup_report: Option<String>,
+
+    /// Append a record of this run (command, posts, ideas, failures, tokens, error) to the Postgres `runs` table
+    #[arg(long, global = true)]
+    pub log_run: bool,
+
+    /// Output Gemini's raw reply unparsed and skip all exports (for prompt debugging)
+    #[arg(long, global = true)]
+    pub raw_ideas: bool,
+
+    /// Read Reddit and Gemini responses from fixture files instead of the network; exports are skipped
+    #[arg(long, global = true)]
+    pub mock: bool,
+
+    /// Milliseconds to wait between posts in batch, subreddit, and multi modes
+    #[arg(long, global = true, default_value_t = 0)]
+    pub delay_ms: u64,
+
+    /// Maximum Reddit and Gemini requests in flight at once, across all posts
+    #[arg(long, global = true)]
+    pub max_concurrent_requests: Option<usize>,
+
+    /// Stop a batch, or move on to the next subreddit in multi mode, after this many posts fail in a row
+    #[arg(long, global = true, value_name = "N")]
+    pub max_consecutive_failures: Option<usize>,
+
+    /// Seconds every Reddit request pauses after Reddit answers 429 Too Many Requests (0 disables)
+    #[arg(long, global = true, default_value_t = 30, value_name = "SECS")]
+    pub rate_limit_cooldown: u64,
+
+    /// Maximum retries (Reddit, Sheets) across the whole run; once spent, failures are not retried
+    #[arg(long, global = true)]
+    pub retry_budget: Option<usize>,
+
+    /// Re-prompt Gemini once with a stricter JSON instruction when no ideas can be parsed
+    #[arg(long, global = true)]
+    pub retry_empty: bool,
+
+    /// When no ideas can be parsed, retry once on a stronger model (see --escalation-model)
+    #[arg(long, global = true)]
+    pub escalate_on_parse_fail: bool,
+
+    /// Model --escalate-on-parse-fail retries on (default: gemini-2.5-pro)
+    #[arg(long, global = true, value_name = "MODEL")]
+    pub escalation_model: Option<String>,
+
+    /// Fail a post if any parsed idea has blank fields or no MVP features, instead of dropping it
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// How a fatal error is printed to stderr: `Error: ...` text, or a JSON object for scripts
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// Field separator for CSV output; use semicolon for Excel in locales with a decimal comma
+    #[arg(long, global = true, value_enum, default_value_t = CsvDelimiter::Comma)]
+    pub csv_delimiter: CsvDelimiter,
+
+    /// Start saved CSV files with a UTF-8 byte order mark so Excel reads accents correctly
+    #[arg(long, global = true)]
+    pub csv_bom: bool,
+
+    /// Order posts in the output by engagement, highest first (default: as fetched)
+    #[arg(long, global = true, value_enum)]
+    pub sort_by: Option<ResultOrder>,
+
+    /// Order of each post's ideas: as the model returned them, quickest to build first, or best rated first
+    #[arg(long, global = true, value_enum, default_value_t = IdeaOrder::Model)]
+    pub sort_ideas: IdeaOrder,
+
+    /// Rate each idea 1-10 for novelty, feasibility, and market fit with a second Gemini call per post
+    #[arg(long, global = true)]
+    pub score_ideas: bool,
+
+    /// Drop ideas whose average --score-ideas rating is below this (1-10)
+    #[arg(long, global = true, value_name = "N", requires = "score_ideas")]
+    pub min_idea_score: Option<f64>,
+
+    /// Re-prompt (up to twice) for more ideas when a post yields fewer than this many
+    #[arg(long, global = true)]
+    pub min_ideas: Option<usize>,
+
+    /// Seconds to wait for a single Gemini call before trying the next model. The default lets
+    /// the whole fallback chain time out within the default --post-timeout
+    #[arg(long, global = true, default_value_t = 25)]
+    pub gemini_timeout: u64,
+
+    /// Seconds allowed to fetch one post and generate its ideas, retries and fallbacks included;
+    /// exports run afterwards (0 = no limit)
+    #[arg(long, global = true, default_value_t = 120)]
+    pub post_timeout: u64,
+
+    /// Hide progress indicators
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+
+    /// Language for generated ideas (en, es, fr, de, pt, it, nl, ja, zh, ko, hi)
+    #[arg(long, global = true, default_value = "en", value_parser = parse_language)]
+    pub lang: &'static str,
+
+    /// Skip posts whose body isn't in one of these languages (comma-separated codes, e.g. en,es); unclear posts are kept
+    #[arg(long, global = true, value_name = "CODES", value_delimiter = ',', value_parser = parse_language_code)]
+    pub lang_filter: Vec<&'static str>,
+
+    /// Log every Gemini model attempt, not just fallbacks and errors
+    #[arg(long, short, global = true)]
+    pub verbose: bool,
+
+    /// Maximum total characters of comment text per post (combined with the comment count cap)
+    #[arg(long, global = true)]
+    pub comment_chars: Option<usize>,
+
+    /// Split a post body longer than --chunk-size into parts, generate ideas for each, and merge them (one Gemini call per part)
+    #[arg(long, global = true)]
+    pub chunk_long_posts: bool,
+
+    /// Maximum characters of post body per part under --chunk-long-posts
+    #[arg(long, global = true, value_name = "CHARS", default_value_t = 12_000)]
+    pub chunk_size: usize,
+
+    /// Truncate any single comment longer than this many characters
+    #[arg(long, global = true, default_value_t = 2000)]
+    pub max_comments_length: usize,
+
+    /// Fetch each subreddit's description (once per run) and give it to Gemini as context
+    #[arg(long, global = true)]
+    pub with_context: bool,
+
+    /// Analyze batch, subreddit, and multi posts even if their URL is already in the configured sheet(s)
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// No longer needed: already-exported posts are skipped by default. Still accepted so older scripts keep working
+    #[arg(long, global = true, hide = true)]
+    pub skip_exported: bool,
+
+    /// Send each image post's image to Gemini along with the text (larger requests)
+    #[arg(long, global = true)]
+    pub vision: bool,
+
+    /// Extra requirement appended to the prompt, e.g. "MVP features must be concrete user-facing capabilities" (repeatable)
+    #[arg(long = "guidance", global = true, value_name = "TEXT")]
+    pub guidance: Vec<String>,
+
+    /// Role Gemini is given as a system instruction (default: a pragmatic product strategist; "" sends none)
+    #[arg(long, global = true, value_name = "TEXT")]
+    pub persona: Option<String>,
+
+    /// Comma-separated categories Gemini tags each idea with (overrides IDEA_CATEGORIES)
+    #[arg(long, global = true, value_name = "LIST")]
+    pub categories: Option<String>,
+
+    /// Only output ideas in these categories (comma-separated); exports still get every idea
+    #[arg(long, global = true, value_name = "LIST", value_delimiter = ',')]
+    pub filter_category: Vec<String>,
+
+    /// How comments go into the prompt: raw, summarized (extra Gemini call), or top-quotes
+    #[arg(long, global = true, value_enum, default_value_t = CommentStrategy::Raw)]
+    pub comment_strategy: CommentStrategy,
+
+    /// Drop comments scored below this before the top ones are taken
+    #[arg(long, global = true, allow_negative_numbers = true)]
+    pub comments_min_score: Option<i64>,
+
+    /// Only substantive, upvoted comments: fetch with sort=top, a minimum score of 10, and up to
+    /// 20 comments per post unless --comments or DEFAULT_COMMENTS says otherwise
+    #[arg(long, global = true)]
+    pub high_signal: bool,
+
+    /// Also include the highest-scored reply under each comment (one level deep), marked as a reply
+    #[arg(long, global = true)]
+    pub with_top_replies: bool,
+
+    /// Tag each comment in the prompt with its id so ideas can be traced back to it
+    #[arg(long, global = true)]
+    pub comment_refs: bool,
+
+    /// Skip Sheets rows whose Post URL + Product Name already exist (costs one extra read per post)
+    #[arg(long, global = true)]
+    pub no_duplicate_rows: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Command {
-    /// Analyze a single Reddit post URL
+    /// Analyze one or more Reddit post URLs
     Analyze {
-        /// Reddit post URL
-        url: String,
+        /// Reddit post URL(s)
+        #[arg(required = true)]
+        urls: Vec<String>,
 
         /// Number of top comments to include
         #[arg(long, default_value_t = 10)]
         comments: usize,
 
+        /// Ignore the post body and generate ideas from comments only
+        #[arg(long)]
+        comments_only: bool,
+
+        /// How Reddit sorts comments before the top ones are taken (default: Reddit's "best")
+        #[arg(long, value_enum)]
+        comment_sort: Option<CommentSort>,
+
+        /// Write the raw Reddit JSON for each post to this file (numbered when several URLs are given)
+        #[arg(long, value_name = "FILE")]
+        dump_raw: Option<String>,
+
         /// Output format
         #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
         format: OutputFormat,
@@ -33,6 +277,10 @@ pub enum Command {
         /// Path to file containing one URL per line
         file: String,
 
+        /// Number of posts processed at the same time
+        #[arg(long, default_value_t = 3)]
+        concurrency: usize,
+
         /// Output format
         #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
         format: OutputFormat,
@@ -47,14 +295,38 @@ pub enum Command {
         /// Subreddit name (without r/)
         name: String,
 
-        /// Number of posts to fetch
-        #[arg(long, default_value_t = 5)]
-        limit: usize,
+        /// Number of posts to fetch (alias: --limit)
+        #[arg(long, alias = "limit", default_value_t = 5)]
+        fetch_limit: usize,
+
+        /// Number of fetched posts to analyze (default: all fetched)
+        #[arg(long)]
+        analyze_limit: Option<usize>,
+
+        /// Analyze a random sample of this many fetched posts instead of the first ones
+        #[arg(long, conflicts_with = "analyze_limit")]
+        sample: Option<usize>,
+
+        /// Seed for --sample, to pick the same posts again (default: random, printed)
+        #[arg(long, requires = "sample")]
+        seed: Option<u64>,
 
         /// Number of top comments per post
         #[arg(long, default_value_t = 10)]
         comments: usize,
 
+        /// Ignore the post body and generate ideas from comments only
+        #[arg(long)]
+        comments_only: bool,
+
+        /// Combine the analyzed posts into one prompt and generate ideas across all of them
+        #[arg(long)]
+        aggregate: bool,
+
+        /// Skip link posts and analyze only text (self) posts
+        #[arg(long)]
+        self_only: bool,
+
         /// Output format
         #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
         format: OutputFormat,
@@ -77,10 +349,26 @@ pub enum Command {
         #[arg(long, default_value_t = 10)]
         comments: usize,
 
+        /// Ignore the post body and generate ideas from comments only
+        #[arg(long)]
+        comments_only: bool,
+
         /// Maximum total ideas to generate (stops early when reached)
         #[arg(long)]
         max_ideas: Option<usize>,
 
+        /// Also scan up to this many related subreddits found by searching for each listed one
+        #[arg(long, default_value_t = 0)]
+        expand: usize,
+
+        /// Skip link posts and analyze only text (self) posts
+        #[arg(long)]
+        self_only: bool,
+
+        /// Resume a run at this subreddit, skipping the ones listed before it
+        #[arg(long)]
+        start_from: Option<String>,
+
         /// Output format
         #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
         format: OutputFormat,
@@ -89,6 +377,137 @@ pub enum Command {
         #[arg(long)]
         save: Option<String>,
     },
+
+    /// Show ideas in a newer saved JSON run that were not in an older one
+    Diff {
+        /// Earlier run saved with --format json
+        old: String,
+
+        /// Later run saved with --format json
+        new: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Save output to file
+        #[arg(long)]
+        save: Option<String>,
+    },
+
+    /// Combine several runs saved with --format json into one report
+    Merge {
+        /// Saved runs or patterns such as "runs/week-*.json" (quote patterns so the tool expands them)
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Drop ideas that repeat one from an earlier file or post (same normalized name or problem)
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Save output to file
+        #[arg(long)]
+        save: Option<String>,
+    },
+
+    /// Send a run saved with --format json to the configured exporters, without re-analyzing
+    Export {
+        /// Run saved with --format json
+        file: String,
+    },
+
+    /// List the Gemini models available to your API key and check the fallback chain
+    Models,
+}
+
+impl Command {
+    /// Whether the command can send results to exporters, and so needs their clients.
+    pub fn exports(&self) -> bool {
+        !matches!(self, Command::Diff { .. } | Command::Merge { .. } | Command::Models)
+    }
+
+    /// Replace the built-in `--comments` and `--limit` defaults with configured ones, leaving
+    /// flags that were given on the command line alone. `matches` are the parsed arguments.
+    pub fn apply_defaults(&mut self, matches: &ArgMatches, comments: Option<usize>, limit: Option<usize>) {
+        let Some((_, sub)) = matches.subcommand() else {
+            return;
+        };
+        let unset = |id: &str| sub.value_source(id) == Some(ValueSource::DefaultValue);
+        let (comments_flag, limit_flag) = match self {
+            Command::Analyze { comments: c, .. } => (c, None),
+            Command::Subreddit {
+                comments: c,
+                fetch_limit: l,
+                ..
+            } => (c, Some((l, "fetch_limit"))),
+            Command::Multi {
+                comments: c,
+                limit: l,
+                ..
+            } => (c, Some((l, "limit"))),
+            _ => return,
+        };
+        if let Some(n) = comments
+            && unset("comments")
+        {
+            *comments_flag = n;
+        }
+        if let (Some((flag, id)), Some(n)) = (limit_flag, limit)
+            && unset(id)
+        {
+            *flag = n;
+        }
+    }
+}
+
+/// Fatal error output styles.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ErrorFormat {
+    Text,
+    /// `{"error": {"type": ..., "message": ..., "exit_code": ...}}` on one line
+    Json,
+}
+
+/// CSV field separators.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CsvDelimiter {
+    Comma,
+    Semicolon,
+}
+
+impl CsvDelimiter {
+    pub fn char(self) -> char {
+        match self {
+            CsvDelimiter::Comma => ',',
+            CsvDelimiter::Semicolon => ';',
+        }
+    }
+}
+
+/// How posts are ordered in the output.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ResultOrder {
+    /// Post score
+    Score,
+    /// Total comment count
+    Comments,
+    /// Number of ideas generated
+    Ideas,
+}
+
+/// How ideas are ordered within a post.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum IdeaOrder {
+    /// The order Gemini returned them in
+    Model,
+    /// Shortest effort estimate first; ideas without one go last
+    Effort,
+    /// Highest average --score-ideas rating first; unscored ideas go last
+    Score,
 }
 
 #[derive(Clone, ValueEnum)]
@@ -96,4 +515,100 @@ pub enum OutputFormat {
     Text,
     Json,
     Markdown,
+    /// Compact markdown with one table row per idea
+    MarkdownTable,
+    Csv,
+    /// Print text to stdout and, with --save, write every format side by side
+    All,
+}
+
+impl OutputFormat {
+    /// Every concrete format written by `--format all`.
+    pub const CONCRETE: &'static [OutputFormat] = &[
+        OutputFormat::Json,
+        OutputFormat::Markdown,
+        OutputFormat::Csv,
+        OutputFormat::Text,
+    ];
+
+    /// File extension used when saving this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text | OutputFormat::All => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Markdown | OutputFormat::MarkdownTable => "md",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Supported `--lang` codes and the language names used in the prompt.
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("de", "German"),
+    ("pt", "Portuguese"),
+    ("it", "Italian"),
+    ("nl", "Dutch"),
+    ("ja", "Japanese"),
+    ("zh", "Chinese"),
+    ("ko", "Korean"),
+    ("hi", "Hindi"),
+];
+
+/// Look up a language code, rejecting codes outside `LANGUAGES`.
+fn find_language(code: &str) -> Result<&'static (&'static str, &'static str), String> {
+    let code = code.trim().to_lowercase();
+    LANGUAGES.iter().find(|(c, _)| *c == code).ok_or_else(|| {
+        let codes: Vec<&str> = LANGUAGES.iter().map(|(c, _)| *c).collect();
+        format!("unsupported language '{}' (expected one of: {})", code, codes.join(", "))
+    })
+}
+
+/// Map a language code to its name, for `--lang`.
+fn parse_language(code: &str) -> Result<&'static str, String> {
+    find_language(code).map(|(_, name)| *name)
+}
+
+/// Normalize a language code, for `--lang-filter`.
+fn parse_language_code(code: &str) -> Result<&'static str, String> {
+    find_language(code).map(|(code, _)| *code)
+}
+
+/// Comment sort orders accepted by Reddit's `?sort=` parameter.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CommentSort {
+    Best,
+    Top,
+    New,
+    Controversial,
+    Old,
+    Qa,
+}
+
+impl CommentSort {
+    /// Value Reddit expects in the query string ("best" is called "confidence").
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            CommentSort::Best => "confidence",
+            CommentSort::Top => "top",
+            CommentSort::New => "new",
+            CommentSort::Controversial => "controversial",
+            CommentSort::Old => "old",
+            CommentSort::Qa => "qa",
+        }
+    }
+}
+
+/// How comments are written into the idea prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum CommentStrategy {
+    /// Every comment in full
+    #[default]
+    Raw,
+    /// Condense comments into key points with a preliminary Gemini call
+    Summarized,
+    /// Only the most informative sentence of each comment
+    TopQuotes,
 }
@@ -1,30 +1,261 @@
 use std::env;
 
+use crate::export::sheets::{self, Column, DEFAULT_COLUMNS};
+use crate::models::DEFAULT_CATEGORIES;
+use crate::services::mock::DEFAULT_FIXTURES_DIR;
+use crate::services::reddit::DEFAULT_USER_AGENT;
+
+/// Values Gemini accepts for a `safetySettings` threshold.
+const SAFETY_THRESHOLDS: &[&str] = &[
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+];
+
 pub struct AppConfig {
     pub gemini_api_key: String,
     pub google_sheet_id: Option<String>,
+    /// Per-subreddit sheet overrides from `GOOGLE_SHEET_ROUTES`, keyed by lowercased subreddit.
+    pub sheet_routes: Vec<(String, String)>,
+    /// Sheet column layout from `SHEETS_COLUMNS`, or the standard one.
+    pub sheet_columns: Vec<Column>,
+    /// Write a row marking the start of each run before its ideas (`SHEETS_RUN_SEPARATOR=1`).
+    pub sheets_run_separator: bool,
     pub google_credentials_path: Option<String>,
+    pub database_url: Option<String>,
+    pub github_token: Option<String>,
+    pub github_repo: Option<String>,
+    pub trello_key: Option<String>,
+    pub trello_token: Option<String>,
+    pub trello_list_id: Option<String>,
+    pub reddit_user_agent: String,
+    pub gemini_safety_threshold: Option<String>,
+    pub comment_weighting: CommentWeighting,
+    /// Categories ideas are tagged with, from `--categories`, `IDEA_CATEGORIES`, or the defaults.
+    pub categories: Vec<String>,
+    /// `--comments` when not passed (`DEFAULT_COMMENTS`); also used by `batch`, which has no flag.
+    pub default_comments: Option<usize>,
+    /// `--limit` for `subreddit` and `multi` when not passed (`DEFAULT_LIMIT`).
+    pub default_limit: Option<usize>,
+    /// Fixture directory when running against canned responses (`--mock` or `MOCK=1`).
+    pub mock_fixtures: Option<String>,
+}
+
+/// How comments are ranked in the prompt: `score * score_weight - depth * depth_penalty`.
+#[derive(Clone, Debug)]
+pub struct CommentWeighting {
+    pub score_weight: f64,
+    pub depth_penalty: f64,
+}
+
+impl Default for CommentWeighting {
+    fn default() -> Self {
+        CommentWeighting {
+            score_weight: 1.0,
+            depth_penalty: 10.0,
+        }
+    }
+}
+
+impl CommentWeighting {
+    pub fn weight(&self, score: i64, depth: u32) -> f64 {
+        score as f64 * self.score_weight - depth as f64 * self.depth_penalty
+    }
 }
 
 impl AppConfig {
-    pub fn load() -> Self {
+    /// Load from the environment and `.env`. Explicit values (from CLI flags) take precedence.
+    pub fn load(
+        gemini_key: Option<String>,
+        sheet_id: Option<String>,
+        credentials_path: Option<String>,
+        categories: Option<String>,
+        mock: bool,
+    ) -> Self {
         dotenvy::dotenv().ok();
 
-        let gemini_api_key = env::var("GEMINI_API_KEY")
-            .expect("GEMINI_API_KEY must be set in environment");
+        let mock_fixtures = (mock || env::var("MOCK").is_ok_and(|v| v.trim() == "1")).then(|| {
+            env::var("MOCK_FIXTURES_DIR")
+                .ok()
+                .filter(|d| !d.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_FIXTURES_DIR.to_string())
+        });
 
-        let google_sheet_id = env::var("GOOGLE_SHEET_ID").ok();
-        let google_credentials_path = env::var("GOOGLE_APPLICATION_CREDENTIALS").ok();
+        // Mock runs never call Gemini, so they work without a key
+        let gemini_api_key = gemini_key
+            .or_else(|| env_secret("GEMINI_API_KEY"))
+            .or_else(|| mock_fixtures.as_ref().map(|_| String::new()))
+            .expect("GEMINI_API_KEY must be set in environment or passed with --gemini-key");
+
+        let google_sheet_id = sheet_id
+            .or_else(|| env::var("GOOGLE_SHEET_ID").ok())
+            .filter(|id| !id.trim().is_empty());
+        let sheet_routes = env::var("GOOGLE_SHEET_ROUTES")
+            .map(|raw| parse_sheet_routes(&raw))
+            .unwrap_or_default();
+        let sheet_columns = match env::var("SHEETS_COLUMNS") {
+            Ok(raw) if !raw.trim().is_empty() => sheets::parse_columns(&raw)
+                .unwrap_or_else(|e| panic!("SHEETS_COLUMNS is invalid: {}", e)),
+            _ => DEFAULT_COLUMNS.to_vec(),
+        };
+        let sheets_run_separator = env::var("SHEETS_RUN_SEPARATOR").is_ok_and(|v| v.trim() == "1");
+        let google_credentials_path =
+            credentials_path.or_else(|| env::var("GOOGLE_APPLICATION_CREDENTIALS").ok());
+        let database_url = env_secret("DATABASE_URL").filter(|u| !u.trim().is_empty());
+        let github_token = env_secret("GITHUB_TOKEN").filter(|t| !t.trim().is_empty());
+        let github_repo = env::var("GITHUB_REPO").ok().filter(|r| !r.trim().is_empty());
+        let trello_key = env_secret("TRELLO_KEY").filter(|k| !k.trim().is_empty());
+        let trello_token = env_secret("TRELLO_TOKEN").filter(|t| !t.trim().is_empty());
+        let trello_list_id = env::var("TRELLO_LIST_ID").ok().filter(|l| !l.trim().is_empty());
+
+        let reddit_user_agent = match env::var("REDDIT_USER_AGENT") {
+            Ok(ua) if ua.trim().is_empty() => {
+                panic!("REDDIT_USER_AGENT must not be empty when set")
+            }
+            Ok(ua) => ua.trim().to_string(),
+            Err(_) => DEFAULT_USER_AGENT.to_string(),
+        };
+
+        let gemini_safety_threshold = env::var("GEMINI_SAFETY_THRESHOLD").ok().map(|t| {
+            let t = t.trim().to_uppercase();
+            if !SAFETY_THRESHOLDS.contains(&t.as_str()) {
+                panic!(
+                    "GEMINI_SAFETY_THRESHOLD must be one of {}, got '{}'",
+                    SAFETY_THRESHOLDS.join(", "),
+                    t
+                );
+            }
+            t
+        });
+
+        let defaults = CommentWeighting::default();
+        let comment_weighting = CommentWeighting {
+            score_weight: env_f64("COMMENT_SCORE_WEIGHT").unwrap_or(defaults.score_weight),
+            depth_penalty: env_f64("COMMENT_DEPTH_PENALTY").unwrap_or(defaults.depth_penalty),
+        };
+
+        let categories = categories
+            .or_else(|| env::var("IDEA_CATEGORIES").ok())
+            .map(|raw| parse_categories(&raw))
+            .filter(|list| !list.is_empty())
+            .unwrap_or_else(|| DEFAULT_CATEGORIES.iter().map(|c| c.to_string()).collect());
+
+        let default_comments = env_usize("DEFAULT_COMMENTS");
+        let default_limit = env_usize("DEFAULT_LIMIT");
 
         AppConfig {
             gemini_api_key,
             google_sheet_id,
+            sheet_routes,
+            sheet_columns,
+            sheets_run_separator,
             google_credentials_path,
+            database_url,
+            github_token,
+            github_repo,
+            trello_key,
+            trello_token,
+            trello_list_id,
+            reddit_user_agent,
+            gemini_safety_threshold,
+            comment_weighting,
+            categories,
+            default_comments,
+            default_limit,
+            mock_fixtures,
         }
     }
 
-    /// Returns true if both Sheet ID and credentials are configured.
+    /// Returns true if credentials and at least one target sheet (default or routed) are configured.
     pub fn sheets_enabled(&self) -> bool {
-        self.google_sheet_id.is_some() && self.google_credentials_path.is_some()
+        (self.google_sheet_id.is_some() || !self.sheet_routes.is_empty())
+            && self.google_credentials_path.is_some()
+    }
+
+    /// Sheet that ideas from `subreddit` go to: its route if one matches, otherwise the default.
+    pub fn sheet_for(&self, subreddit: &str) -> Option<&str> {
+        let subreddit = subreddit.to_lowercase();
+        self.sheet_routes
+            .iter()
+            .find(|(sub, _)| *sub == subreddit)
+            .map(|(_, id)| id.as_str())
+            .or(self.google_sheet_id.as_deref())
+    }
+
+    /// Returns true if both a GitHub token and an `owner/repo` target are configured.
+    pub fn github_enabled(&self) -> bool {
+        self.github_token.is_some() && self.github_repo.is_some()
+    }
+
+    /// Returns true if a Trello key, token, and target list are all configured.
+    pub fn trello_enabled(&self) -> bool {
+        self.trello_key.is_some() && self.trello_token.is_some() && self.trello_list_id.is_some()
+    }
+
+    /// Returns true if a Postgres connection string is configured.
+    pub fn postgres_enabled(&self) -> bool {
+        self.database_url.is_some()
+    }
+}
+
+/// Parse `sub=sheetId,sub2=sheetId2`. Panics on an entry without both parts.
+fn parse_sheet_routes(raw: &str) -> Vec<(String, String)> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((sub, id)) if !sub.trim().is_empty() && !id.trim().is_empty() => (
+                sub.trim().trim_start_matches("r/").to_lowercase(),
+                id.trim().to_string(),
+            ),
+            _ => panic!(
+                "GOOGLE_SHEET_ROUTES entries must look like subreddit=SHEET_ID, got '{}'",
+                entry
+            ),
+        })
+        .collect()
+}
+
+/// Read a secret from `key`, or else from the file named by `<key>_FILE` (Docker/Kubernetes
+/// secret mounts), with trailing whitespace and newlines trimmed. Panics if that file can't be read.
+fn env_secret(key: &str) -> Option<String> {
+    if let Ok(value) = env::var(key) {
+        return Some(value);
+    }
+    let file_key = format!("{}_FILE", key);
+    let path = env::var(&file_key).ok().filter(|p| !p.trim().is_empty())?;
+    match std::fs::read_to_string(path.trim()) {
+        Ok(content) => Some(content.trim_end().to_string()),
+        Err(e) => panic!("{} points to {}, which could not be read: {}", file_key, path, e),
+    }
+}
+
+/// Parse a comma-separated category list, lowercased, without blanks or repeats.
+fn parse_categories(raw: &str) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for category in raw.split(',').map(|c| c.trim().to_lowercase()) {
+        if !category.is_empty() && !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    categories
+}
+
+/// Read a count from an env var. Panics if set but not a non-negative integer.
+fn env_usize(key: &str) -> Option<usize> {
+    let raw = env::var(key).ok().filter(|v| !v.trim().is_empty())?;
+    match raw.trim().parse::<usize>() {
+        Ok(v) => Some(v),
+        Err(_) => panic!("{} must be a whole number, got '{}'", key, raw),
+    }
+}
+
+/// Read a numeric env var. Panics if set but not a valid number.
+fn env_f64(key: &str) -> Option<f64> {
+    let raw = env::var(key).ok()?;
+    match raw.trim().parse::<f64>() {
+        Ok(v) => Some(v),
+        Err(_) => panic!("{} must be a number, got '{}'", key, raw),
     }
 }
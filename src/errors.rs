@@ -1,10 +1,50 @@
 use std::fmt;
 
+#[derive(Debug)]
 pub enum AppError {
     InvalidInput(String),
     ExternalService(String),
+    ContentBlocked(String),
+    /// A post took longer than `--post-timeout` and was cancelled.
+    Timeout(String),
+    /// A post was passed over on purpose, e.g. by `--lang-filter`.
+    Skipped(String),
     Io(String),
     SheetsExport(String),
+    PostgresExport(String),
+    GithubExport(String),
+    TrelloExport(String),
+}
+
+impl AppError {
+    /// The variant name, e.g. `ExternalService`, for `--error-format json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::ExternalService(_) => "ExternalService",
+            AppError::ContentBlocked(_) => "ContentBlocked",
+            AppError::Timeout(_) => "Timeout",
+            AppError::Skipped(_) => "Skipped",
+            AppError::Io(_) => "Io",
+            AppError::SheetsExport(_) => "SheetsExport",
+            AppError::PostgresExport(_) => "PostgresExport",
+            AppError::GithubExport(_) => "GithubExport",
+            AppError::TrelloExport(_) => "TrelloExport",
+        }
+    }
+
+    /// Process exit code for this class of failure, so scripts can tell them apart.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::InvalidInput(_) | AppError::Skipped(_) => 2,
+            AppError::ExternalService(_) | AppError::ContentBlocked(_) | AppError::Timeout(_) => 3,
+            AppError::Io(_) => 4,
+            AppError::SheetsExport(_)
+            | AppError::PostgresExport(_)
+            | AppError::GithubExport(_)
+            | AppError::TrelloExport(_) => 5,
+        }
+    }
 }
 
 impl fmt::Display for AppError {
@@ -12,8 +52,14 @@ impl fmt::Display for AppError {
         match self {
             AppError::InvalidInput(msg) => write!(f, "{}", msg),
             AppError::ExternalService(msg) => write!(f, "{}", msg),
+            AppError::ContentBlocked(msg) => write!(f, "{}", msg),
+            AppError::Timeout(msg) => write!(f, "{}", msg),
+            AppError::Skipped(msg) => write!(f, "{}", msg),
             AppError::Io(msg) => write!(f, "{}", msg),
             AppError::SheetsExport(msg) => write!(f, "Sheets export: {}", msg),
+            AppError::PostgresExport(msg) => write!(f, "Postgres export: {}", msg),
+            AppError::GithubExport(msg) => write!(f, "GitHub export: {}", msg),
+            AppError::TrelloExport(msg) => write!(f, "Trello export: {}", msg),
         }
     }
 }
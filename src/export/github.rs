@@ -0,0 +1,54 @@
+use crate::errors::AppError;
+use crate::models::AnalysisResult;
+
+const GITHUB_API: &str = "https://api.github.com";
+const ISSUE_LABEL: &str = "reddit-idea";
+
+/// Create one GitHub issue per idea, for the first `top_n` ideas of a post.
+///
+/// `repo` is `owner/name`. Issues are titled with the product name and
+/// labeled `reddit-idea`, plus the idea's category if it has one. Returns the number of issues created.
+pub async fn create_issues(
+    client: &reqwest::Client,
+    token: &str,
+    repo: &str,
+    result: &AnalysisResult,
+    top_n: usize,
+) -> Result<usize, AppError> {
+    let url = format!("{}/repos/{}/issues", GITHUB_API, repo);
+    let mut created = 0;
+
+    for idea in result.ideas.iter().take(top_n) {
+        let mut labels = vec![ISSUE_LABEL];
+        if !idea.category.is_empty() {
+            labels.push(&idea.category);
+        }
+        let payload = serde_json::json!({
+            "title": idea.product_name,
+            "body": super::idea_body(idea, result),
+            "labels": labels,
+        });
+
+        let res = client
+            .post(&url)
+            .bearer_auth(token)
+            .header("User-Agent", "reddit-research-engine")
+            .header("Accept", "application/vnd.github+json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| AppError::GithubExport(format!("Failed to contact GitHub: {}", e)))?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let error_text = res.text().await.unwrap_or_default();
+            return Err(AppError::GithubExport(format!(
+                "Creating issue '{}' failed ({}): {}",
+                idea.product_name, status, error_text
+            )));
+        }
+        created += 1;
+    }
+
+    Ok(created)
+}
@@ -1 +1,40 @@
+use crate::models::{AnalysisResult, Idea};
+
+pub mod github;
+pub mod postgres;
 pub mod sheets;
+pub mod trello;
+
+/// Markdown description of one idea with a link back to its post, shared by GitHub issues
+/// and Trello cards.
+fn idea_body(idea: &Idea, result: &AnalysisResult) -> String {
+    let mut body = String::new();
+    body.push_str(&format!("**Target User:** {}\n\n", idea.target_user));
+    body.push_str(&format!("**Core Problem:** {}\n\n", idea.core_problem));
+    body.push_str("**MVP Features:**\n");
+    for feat in &idea.mvp_features {
+        body.push_str(&format!("- {}\n", feat));
+    }
+    body.push_str(&format!("\n**Monetization:** {}\n\n", idea.monetization));
+    body.push_str(&format!("**Why Feasible for Solo Builder:** {}\n\n", idea.feasibility));
+    if !idea.effort_estimate.trim().is_empty() {
+        body.push_str(&format!("**Effort Estimate:** {}\n\n", idea.effort_estimate));
+    }
+    if !idea.category.is_empty() {
+        body.push_str(&format!("**Category:** {}\n\n", idea.category));
+    }
+    if let Some(scores) = &idea.scores {
+        body.push_str(&format!(
+            "**Scores:** novelty {}/10, feasibility {}/10, market fit {}/10\n\n",
+            scores.novelty, scores.feasibility, scores.market_fit
+        ));
+    }
+    if !idea.source_evidence.trim().is_empty() {
+        body.push_str(&format!("**Source Evidence:** {}\n\n", idea.source_evidence));
+    }
+    body.push_str(&format!(
+        "---\nSource: [{}]({}) in r/{}\n",
+        result.title, result.url, result.subreddit
+    ));
+    body
+}
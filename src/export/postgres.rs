@@ -0,0 +1,201 @@
+use crate::errors::AppError;
+use crate::models::AnalysisResult;
+
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, NoTls};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS ideas (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    subreddit TEXT NOT NULL,
+    post_url TEXT NOT NULL,
+    post_title TEXT NOT NULL,
+    product_name TEXT NOT NULL,
+    target_user TEXT NOT NULL,
+    core_problem TEXT NOT NULL,
+    mvp_features TEXT[] NOT NULL,
+    monetization TEXT NOT NULL,
+    feasibility TEXT NOT NULL,
+    idea_id TEXT,
+    source_evidence TEXT,
+    effort_estimate TEXT,
+    category TEXT,
+    novelty_score SMALLINT,
+    feasibility_score SMALLINT,
+    market_fit_score SMALLINT
+);
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS idea_id TEXT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS source_evidence TEXT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS effort_estimate TEXT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS category TEXT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS novelty_score SMALLINT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS feasibility_score SMALLINT;
+ALTER TABLE ideas ADD COLUMN IF NOT EXISTS market_fit_score SMALLINT;
+CREATE UNIQUE INDEX IF NOT EXISTS ideas_post_url_idea_id ON ideas (post_url, idea_id)";
+
+const INSERT_IDEAS: &str = "INSERT INTO ideas
+    (subreddit, post_url, post_title, product_name, target_user,
+     core_problem, mvp_features, monetization, feasibility, idea_id, source_evidence,
+     effort_estimate, category, novelty_score, feasibility_score, market_fit_score)
+    VALUES";
+
+/// Parameters per row of [`INSERT_IDEAS`].
+const IDEA_PARAMS: usize = 16;
+
+const CREATE_RUNS_TABLE: &str = "CREATE TABLE IF NOT EXISTS runs (
+    id BIGSERIAL PRIMARY KEY,
+    finished_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    command TEXT[] NOT NULL,
+    tool_version TEXT NOT NULL,
+    elapsed_secs DOUBLE PRECISION NOT NULL,
+    posts BIGINT NOT NULL,
+    ideas BIGINT NOT NULL,
+    posts_failed BIGINT NOT NULL,
+    total_tokens BIGINT NOT NULL,
+    models TEXT[] NOT NULL,
+    error TEXT
+)";
+
+const INSERT_RUN: &str = "INSERT INTO runs
+    (command, tool_version, elapsed_secs, posts, ideas, posts_failed, total_tokens, models, error)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+
+/// One invocation of the tool, as stored in the `runs` table.
+pub struct RunRecord {
+    /// Arguments with secrets redacted.
+    pub command: Vec<String>,
+    pub elapsed_secs: f64,
+    pub posts: i64,
+    pub ideas: i64,
+    /// Posts that failed or timed out (skipped posts don't count).
+    pub posts_failed: i64,
+    pub total_tokens: i64,
+    /// Models that answered at least once.
+    pub models: Vec<String>,
+    /// The error that ended the run, if it didn't succeed.
+    pub error: Option<String>,
+}
+
+async fn connect(database_url: &str) -> Result<Client, AppError> {
+    let (client, connection) = tokio_postgres::connect(database_url, NoTls)
+        .await
+        .map_err(|e| AppError::PostgresExport(format!("Failed to connect: {}", e)))?;
+
+    // The connection object drives the socket; it resolves when the client is dropped
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("Postgres connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+/// Connection to the ideas database. Build one per run with [`build_postgres_client`];
+/// concurrent posts share it.
+pub struct PostgresClient {
+    client: Client,
+}
+
+/// Connect once for the whole run and make sure the `ideas` table exists.
+///
+/// The table is created on first use, and older tables get any columns added since.
+pub async fn build_postgres_client(database_url: &str) -> Result<PostgresClient, AppError> {
+    let client = connect(database_url).await?;
+    client
+        .batch_execute(CREATE_TABLE)
+        .await
+        .map_err(|e| AppError::PostgresExport(format!("Failed to create ideas table: {}", e)))?;
+    Ok(PostgresClient { client })
+}
+
+/// Insert a post's ideas into the `ideas` table with one multi-row `INSERT`, so a post's
+/// ideas are stored all together or not at all.
+///
+/// Columns mirror the Sheets export, with `created_at` filled in by the database. An idea
+/// already stored for the same post (same `idea_id`) is not inserted again. Returns the
+/// number of new rows.
+pub async fn insert_ideas_batch(postgres: &PostgresClient, result: &AnalysisResult) -> Result<u64, AppError> {
+    if result.ideas.is_empty() {
+        return Ok(0);
+    }
+
+    // Scores are NULL for ideas `--score-ideas` didn't rate
+    let scores: Vec<[Option<i16>; 3]> = result
+        .ideas
+        .iter()
+        .map(|idea| match &idea.scores {
+            Some(s) => [s.novelty, s.feasibility, s.market_fit].map(|n| Some(n as i16)),
+            None => [None; 3],
+        })
+        .collect();
+
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::with_capacity(result.ideas.len() * IDEA_PARAMS);
+    let mut rows = Vec::with_capacity(result.ideas.len());
+    for (idea, [novelty, feasibility, market_fit]) in result.ideas.iter().zip(&scores) {
+        let first = params.len() + 1;
+        let placeholders: Vec<String> = (first..first + IDEA_PARAMS).map(|n| format!("${}", n)).collect();
+        rows.push(format!("({})", placeholders.join(", ")));
+        params.extend([
+            &result.subreddit as &(dyn ToSql + Sync),
+            &result.url,
+            &result.title,
+            &idea.product_name,
+            &idea.target_user,
+            &idea.core_problem,
+            &idea.mvp_features,
+            &idea.monetization,
+            &idea.feasibility,
+            &idea.id,
+            &idea.source_evidence,
+            &idea.effort_estimate,
+            &idea.category,
+            novelty,
+            feasibility,
+            market_fit,
+        ]);
+    }
+    let query = format!(
+        "{} {} ON CONFLICT (post_url, idea_id) DO NOTHING",
+        INSERT_IDEAS,
+        rows.join(", ")
+    );
+
+    postgres
+        .client
+        .execute(query.as_str(), &params)
+        .await
+        .map_err(|e| AppError::PostgresExport(format!("Failed to insert ideas: {}", e)))
+}
+
+/// Append a row describing this run to the `runs` table (`--log-run`).
+///
+/// The table is created on first use and only ever inserted into, so it builds
+/// up a history of every logged run alongside the `ideas` it produced.
+pub async fn insert_run(database_url: &str, run: &RunRecord) -> Result<(), AppError> {
+    let client = connect(database_url).await?;
+
+    client
+        .batch_execute(CREATE_RUNS_TABLE)
+        .await
+        .map_err(|e| AppError::PostgresExport(format!("Failed to create runs table: {}", e)))?;
+
+    client
+        .execute(
+            INSERT_RUN,
+            &[
+                &run.command,
+                &env!("CARGO_PKG_VERSION"),
+                &run.elapsed_secs,
+                &run.posts,
+                &run.ideas,
+                &run.posts_failed,
+                &run.total_tokens,
+                &run.models,
+                &run.error,
+            ],
+        )
+        .await
+        .map_err(|e| AppError::PostgresExport(format!("Failed to insert run: {}", e)))?;
+
+    Ok(())
+}
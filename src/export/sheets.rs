@@ -1,66 +1,467 @@
 use crate::errors::AppError;
-use crate::models::Idea;
+use crate::models::{stable_hash, AnalysisResult, Idea, IdeaScores};
+use crate::utils::backoff::{retry_transient, Attempt, BackoffPolicy};
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
 
 use chrono::Utc;
-use google_sheets4::api::ValueRange;
+use google_sheets4::api::{
+    BatchUpdateSpreadsheetRequest, DimensionProperties, DimensionRange, Request, UpdateDimensionPropertiesRequest,
+    ValueRange,
+};
 use google_sheets4::Sheets;
 
+/// Authenticated Sheets API client. Build one per run with [`build_sheets_client`];
+/// its authenticator refreshes the access token whenever it expires.
+pub type SheetsClient = Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
+/// Sheets whose batch-key column has been hidden this run.
+static HIDDEN_KEY_COLUMNS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// A field that can be written to a sheet column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Date,
+    Subreddit,
+    PostUrl,
+    PostTitle,
+    ProductName,
+    TargetUser,
+    CoreProblem,
+    MvpFeatures,
+    Monetization,
+    Feasibility,
+    IdeaId,
+    SourceEvidence,
+    EffortEstimate,
+    Category,
+    /// 1-10 ratings from `--score-ideas`; empty when the idea wasn't scored.
+    NoveltyScore,
+    FeasibilityScore,
+    MarketFitScore,
+    /// Left empty, for columns the user fills in by hand (e.g. "Status").
+    Blank,
+}
+
+impl Column {
+    /// Header text for this column, as used in the CSV output.
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Date => "Date",
+            Column::Subreddit => "Subreddit",
+            Column::PostUrl => "Post URL",
+            Column::PostTitle => "Post Title",
+            Column::ProductName => "Product Name",
+            Column::TargetUser => "Target User",
+            Column::CoreProblem => "Core Problem",
+            Column::MvpFeatures => "MVP Features",
+            Column::Monetization => "Monetization",
+            Column::Feasibility => "Feasibility",
+            Column::IdeaId => "Idea ID",
+            Column::SourceEvidence => "Source Evidence",
+            Column::EffortEstimate => "Effort Estimate",
+            Column::Category => "Category",
+            Column::NoveltyScore => "Novelty Score",
+            Column::FeasibilityScore => "Feasibility Score",
+            Column::MarketFitScore => "Market Fit Score",
+            Column::Blank => "",
+        }
+    }
+
+    /// The cell for one idea. `timestamp` fills the Date column.
+    pub fn cell(self, result: &AnalysisResult, idea: &Idea, timestamp: &str) -> String {
+        match self {
+            Column::Date => timestamp.to_string(),
+            Column::Subreddit => result.subreddit.clone(),
+            Column::PostUrl => result.url.clone(),
+            Column::PostTitle => result.title.clone(),
+            Column::ProductName => idea.product_name.clone(),
+            Column::TargetUser => idea.target_user.clone(),
+            Column::CoreProblem => idea.core_problem.clone(),
+            Column::MvpFeatures => idea.mvp_features.join("; "),
+            Column::Monetization => idea.monetization.clone(),
+            Column::Feasibility => idea.feasibility.clone(),
+            Column::IdeaId => idea.id.clone(),
+            Column::SourceEvidence => idea.source_evidence.clone(),
+            Column::EffortEstimate => idea.effort_estimate.clone(),
+            Column::Category => idea.category.clone(),
+            Column::NoveltyScore => score_cell(idea, |s| s.novelty),
+            Column::FeasibilityScore => score_cell(idea, |s| s.feasibility),
+            Column::MarketFitScore => score_cell(idea, |s| s.market_fit),
+            Column::Blank => String::new(),
+        }
+    }
+}
+
+/// The standard layout, used unless `SHEETS_COLUMNS` says otherwise.
+pub const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Date,
+    Column::Subreddit,
+    Column::PostUrl,
+    Column::PostTitle,
+    Column::ProductName,
+    Column::TargetUser,
+    Column::CoreProblem,
+    Column::MvpFeatures,
+    Column::Monetization,
+    Column::Feasibility,
+    Column::IdeaId,
+    Column::SourceEvidence,
+    Column::EffortEstimate,
+    Column::Category,
+    Column::NoveltyScore,
+    Column::FeasibilityScore,
+    Column::MarketFitScore,
+];
+
+fn score_cell(idea: &Idea, rating: fn(&IdeaScores) -> u8) -> String {
+    idea.scores.as_ref().map(|s| rating(s).to_string()).unwrap_or_default()
+}
+
+/// Names accepted in `SHEETS_COLUMNS`, in default order, plus `blank`.
+const COLUMN_NAMES: &[(&str, Column)] = &[
+    ("date", Column::Date),
+    ("subreddit", Column::Subreddit),
+    ("post_url", Column::PostUrl),
+    ("post_title", Column::PostTitle),
+    ("product_name", Column::ProductName),
+    ("target_user", Column::TargetUser),
+    ("core_problem", Column::CoreProblem),
+    ("mvp_features", Column::MvpFeatures),
+    ("monetization", Column::Monetization),
+    ("feasibility", Column::Feasibility),
+    ("idea_id", Column::IdeaId),
+    ("source_evidence", Column::SourceEvidence),
+    ("effort_estimate", Column::EffortEstimate),
+    ("category", Column::Category),
+    ("novelty_score", Column::NoveltyScore),
+    ("feasibility_score", Column::FeasibilityScore),
+    ("market_fit_score", Column::MarketFitScore),
+    ("blank", Column::Blank),
+];
+
+/// Parse a comma-separated column list such as `date,product_name,blank,post_url`.
+pub fn parse_columns(raw: &str) -> Result<Vec<Column>, String> {
+    let columns = raw
+        .split(',')
+        .map(|name| {
+            let name = name.trim().to_lowercase();
+            COLUMN_NAMES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, c)| *c)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = COLUMN_NAMES.iter().map(|(n, _)| *n).collect();
+                    format!("unknown column '{}' (expected one of: {})", name, names.join(", "))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if columns.iter().all(|c| *c == Column::Blank) {
+        return Err("at least one non-blank column is required".into());
+    }
+    Ok(columns)
+}
+
+/// Outcome of an append: how many rows were written and how many were skipped as duplicates.
+pub struct AppendReport {
+    pub appended: usize,
+    pub skipped: usize,
+}
+
 /// Append a batch of ideas as rows to Google Sheet in a single API call.
 ///
-/// Each row contains 10 columns:
-/// Date | Subreddit | Post URL | Post Title | Product Name |
-/// Target User | Core Problem | MVP Features | Monetization | Feasibility
+/// Each row holds one cell per entry in `columns`. The default layout is 17 columns:
+/// Date | Subreddit | Post URL | Post Title | Product Name | Target User | Core Problem |
+/// MVP Features | Monetization | Feasibility | Idea ID | Source Evidence | Effort Estimate | Category |
+/// Novelty Score | Feasibility Score | Market Fit Score
+///
+/// With `skip_duplicates`, existing Post URL + Product Name pairs are read first
+/// and matching ideas are not appended again. That needs both columns in the layout.
+///
+/// Every row also gets a batch key in a hidden column after the layout. If an append
+/// fails and is retried, the sheet is checked for that key first, so an append that
+/// reached the sheet before the error is not written twice.
 pub async fn append_ideas_batch(
+    client: &SheetsClient,
     sheet_id: &str,
-    credentials_path: &str,
-    subreddit: &str,
-    post_url: &str,
-    post_title: &str,
-    ideas: &[Idea],
-) -> Result<(), AppError> {
-    let client = build_sheets_client(credentials_path).await?;
+    result: &AnalysisResult,
+    columns: &[Column],
+    skip_duplicates: bool,
+) -> Result<AppendReport, AppError> {
+    let existing = if skip_duplicates {
+        read_existing_keys(client, sheet_id, columns).await?
+    } else {
+        HashSet::new()
+    };
 
     let timestamp = Utc::now().to_rfc3339();
 
-    let rows: Vec<Vec<serde_json::Value>> = ideas
+    let new_ideas: Vec<_> = result
+        .ideas
+        .iter()
+        .filter(|idea| !existing.contains(&duplicate_key(&result.url, &idea.product_name)))
+        .collect();
+    let skipped = result.ideas.len() - new_ideas.len();
+
+    if new_ideas.is_empty() {
+        return Ok(AppendReport { appended: 0, skipped });
+    }
+
+    let ids: Vec<&str> = new_ideas.iter().map(|idea| idea.id.as_str()).collect();
+    let batch_key = stable_hash(&format!("{}\0{}\0{}", result.url, ids.join(","), timestamp));
+
+    let rows: Vec<Vec<serde_json::Value>> = new_ideas
         .iter()
         .map(|idea| {
-            vec![
-                serde_json::Value::String(timestamp.clone()),
-                serde_json::Value::String(subreddit.to_string()),
-                serde_json::Value::String(post_url.to_string()),
-                serde_json::Value::String(post_title.to_string()),
-                serde_json::Value::String(idea.product_name.clone()),
-                serde_json::Value::String(idea.target_user.clone()),
-                serde_json::Value::String(idea.core_problem.clone()),
-                serde_json::Value::String(idea.mvp_features.join("; ")),
-                serde_json::Value::String(idea.monetization.clone()),
-                serde_json::Value::String(idea.feasibility.clone()),
-            ]
+            columns
+                .iter()
+                .map(|column| serde_json::Value::String(column.cell(result, idea, &timestamp)))
+                .chain([serde_json::Value::String(batch_key.clone())])
+                .collect()
         })
         .collect();
 
+    let key_column = column_letter(columns.len());
+    let range = format!("Sheet1!A:{}", key_column);
     let value_range = ValueRange {
-        range: Some("Sheet1!A:J".to_string()),
+        range: Some(range.clone()),
         major_dimension: Some("ROWS".to_string()),
         values: Some(rows),
     };
 
+    let retrying = AtomicBool::new(false);
+    retry_transient(&BackoffPolicy::default(), || async {
+        if retrying.swap(true, Ordering::SeqCst)
+            && batch_written(client, sheet_id, &key_column, &batch_key)
+                .await
+                .map_err(Attempt::Transient)?
+        {
+            return Ok(());
+        }
+        client
+            .spreadsheets()
+            .values_append(value_range.clone(), sheet_id, &range)
+            .value_input_option("USER_ENTERED")
+            .insert_data_option("INSERT_ROWS")
+            .doit()
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                let transient = is_transient(&e);
+                let e = AppError::SheetsExport(format!("Failed to append rows: {}", e));
+                if transient { Attempt::Transient(e) } else { Attempt::Permanent(e) }
+            })
+    })
+    .await?;
+
+    let hidden = HIDDEN_KEY_COLUMNS.lock().unwrap().contains(sheet_id);
+    if !hidden {
+        hide_key_column(client, sheet_id, columns.len()).await?;
+        HIDDEN_KEY_COLUMNS.lock().unwrap().insert(sheet_id.to_string());
+    }
+
+    Ok(AppendReport {
+        appended: new_ideas.len(),
+        skipped,
+    })
+}
+
+/// Whether a Sheets API error might clear up on retry: network failures, 429, and 5xx.
+/// Other 4xx responses (bad range, no permission, missing sheet) fail the same way every time.
+fn is_transient(error: &google_sheets4::Error) -> bool {
+    use google_sheets4::Error;
+    match error {
+        Error::HttpError(_) | Error::Io(_) => true,
+        Error::Failure(response) => response.status() == 429 || response.status().is_server_error(),
+        Error::BadRequest(body) => body["error"]["code"]
+            .as_u64()
+            .is_some_and(|code| code == 429 || code >= 500),
+        _ => false,
+    }
+}
+
+/// Append a one-cell row in column A marking the start of a run, so runs are easy to tell apart.
+pub async fn append_separator(client: &SheetsClient, sheet_id: &str, label: &str) -> Result<(), AppError> {
+    let range = "Sheet1!A:A";
+    let value_range = ValueRange {
+        range: Some(range.to_string()),
+        major_dimension: Some("ROWS".to_string()),
+        values: Some(vec![vec![serde_json::Value::String(label.to_string())]]),
+    };
     client
         .spreadsheets()
-        .values_append(value_range, sheet_id, "Sheet1!A:J")
-        .value_input_option("USER_ENTERED")
+        .values_append(value_range, sheet_id, range)
+        .value_input_option("RAW")
         .insert_data_option("INSERT_ROWS")
         .doit()
         .await
-        .map_err(|e| AppError::SheetsExport(format!("Failed to append rows: {}", e)))?;
+        .map(|_| ())
+        .map_err(|e| AppError::SheetsExport(format!("Failed to write run separator: {}", e)))
+}
+
+/// Hide Sheet1's batch-key column, at zero-based `index`, so it stays out of the way of the layout.
+async fn hide_key_column(client: &SheetsClient, sheet_id: &str, index: usize) -> Result<(), AppError> {
+    let (_, spreadsheet) = client
+        .spreadsheets()
+        .get(sheet_id)
+        // The appends' scope, so the run's token covers this read too.
+        .add_scope(google_sheets4::api::Scope::Drive)
+        .doit()
+        .await
+        .map_err(|e| AppError::SheetsExport(format!("Failed to read sheet properties: {}", e)))?;
+    let gid = spreadsheet
+        .sheets
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|sheet| sheet.properties)
+        .find(|properties| properties.title.as_deref() == Some("Sheet1"))
+        .and_then(|properties| properties.sheet_id)
+        .ok_or_else(|| AppError::SheetsExport("Spreadsheet has no Sheet1".to_string()))?;
 
-    Ok(())
+    let request = BatchUpdateSpreadsheetRequest {
+        requests: Some(vec![Request {
+            update_dimension_properties: Some(UpdateDimensionPropertiesRequest {
+                range: Some(DimensionRange {
+                    sheet_id: Some(gid),
+                    dimension: Some("COLUMNS".to_string()),
+                    start_index: Some(index as i32),
+                    end_index: Some(index as i32 + 1),
+                }),
+                properties: Some(DimensionProperties {
+                    hidden_by_user: Some(true),
+                    ..Default::default()
+                }),
+                fields: "hiddenByUser".parse().ok(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    client
+        .spreadsheets()
+        .batch_update(request, sheet_id)
+        .doit()
+        .await
+        .map(|_| ())
+        .map_err(|e| AppError::SheetsExport(format!("Failed to hide the batch key column: {}", e)))
 }
 
-async fn build_sheets_client(
-    credentials_path: &str,
-) -> Result<Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>, AppError> {
+/// Whether a row carrying `batch_key` is already in the sheet's batch-key column.
+async fn batch_written(
+    client: &SheetsClient,
+    sheet_id: &str,
+    key_column: &str,
+    batch_key: &str,
+) -> Result<bool, AppError> {
+    let (_, range) = client
+        .spreadsheets()
+        .values_get(sheet_id, &format!("Sheet1!{}:{}", key_column, key_column))
+        .doit()
+        .await
+        .map_err(|e| AppError::SheetsExport(format!("Failed to check for a partial append: {}", e)))?;
+
+    Ok(range
+        .values
+        .unwrap_or_default()
+        .iter()
+        .any(|row| row.first().and_then(|v| v.as_str()) == Some(batch_key)))
+}
+
+/// Post URLs already in the Post URL column, as [`post_key`]s.
+pub async fn read_exported_posts(
+    client: &SheetsClient,
+    sheet_id: &str,
+    columns: &[Column],
+) -> Result<HashSet<String>, AppError> {
+    let letter = column_letter(position(columns, Column::PostUrl)?);
+    let (_, range) = client
+        .spreadsheets()
+        .values_get(sheet_id, &format!("Sheet1!{}:{}", letter, letter))
+        .doit()
+        .await
+        .map_err(|e| AppError::SheetsExport(format!("Failed to read exported posts: {}", e)))?;
+
+    Ok(range
+        .values
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|row| row.first().and_then(|v| v.as_str()))
+        .map(post_key)
+        .collect())
+}
+
+/// Compare posts by path so `www.`, `old.`, and trailing-slash variants of a URL match.
+pub fn post_key(url: &str) -> String {
+    let path = url.split_once("reddit.com").map_or(url, |(_, path)| path);
+    path.trim_end_matches('/').to_lowercase()
+}
+
+/// What makes a row a duplicate: the same post, however its URL is written, and product name.
+fn duplicate_key(url: &str, product_name: &str) -> (String, String) {
+    (post_key(url), product_name.to_string())
+}
+
+/// Read (Post URL, Product Name) pairs already present in the sheet, as [`duplicate_key`]s.
+async fn read_existing_keys(
+    client: &SheetsClient,
+    sheet_id: &str,
+    columns: &[Column],
+) -> Result<HashSet<(String, String)>, AppError> {
+    let url_col = position(columns, Column::PostUrl)?;
+    let name_col = position(columns, Column::ProductName)?;
+    let first = url_col.min(name_col);
+    let range = format!(
+        "Sheet1!{}:{}",
+        column_letter(first),
+        column_letter(url_col.max(name_col))
+    );
+    let (_, range) = client
+        .spreadsheets()
+        .values_get(sheet_id, &range)
+        .doit()
+        .await
+        .map_err(|e| AppError::SheetsExport(format!("Failed to read existing rows: {}", e)))?;
+
+    let cell = |row: &[serde_json::Value], i: usize| {
+        row.get(i).and_then(|v| v.as_str()).unwrap_or("").to_string()
+    };
+
+    Ok(range
+        .values
+        .unwrap_or_default()
+        .iter()
+        .map(|row| duplicate_key(&cell(row, url_col - first), &cell(row, name_col - first)))
+        .collect())
+}
+
+/// Index of `column` in the layout, or an error naming the missing field.
+fn position(columns: &[Column], column: Column) -> Result<usize, AppError> {
+    columns.iter().position(|c| *c == column).ok_or_else(|| {
+        let name = COLUMN_NAMES
+            .iter()
+            .find(|(_, c)| *c == column)
+            .map_or("?", |(n, _)| *n);
+        AppError::SheetsExport(format!("SHEETS_COLUMNS has no '{}' column, which this needs", name))
+    })
+}
+
+/// Spreadsheet letter for a 0-based column index: 0 → A, 25 → Z, 26 → AA.
+fn column_letter(index: usize) -> String {
+    let mut n = index + 1;
+    let mut letters = Vec::new();
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push((b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Read the service-account key and set up the authenticator and HTTPS client.
+pub async fn build_sheets_client(credentials_path: &str) -> Result<SheetsClient, AppError> {
     let secret = yup_oauth2::read_service_account_key(credentials_path)
         .await
         .map_err(|e| {
@@ -86,3 +487,131 @@ async fn build_sheets_client(
 
     Ok(Sheets::new(hyper_client, auth))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_key_matches_url_variants_of_one_post() {
+        let key = duplicate_key("https://www.reddit.com/r/freelance/comments/abc/unpaid_invoices", "PayChase");
+        for url in [
+            "https://old.reddit.com/r/freelance/comments/abc/unpaid_invoices",
+            "https://reddit.com/r/freelance/comments/abc/unpaid_invoices/",
+        ] {
+            assert_eq!(duplicate_key(url, "PayChase"), key);
+        }
+        assert_ne!(duplicate_key("https://old.reddit.com/r/freelance/comments/xyz/other", "PayChase"), key);
+        assert_ne!(
+            duplicate_key("https://old.reddit.com/r/freelance/comments/abc/unpaid_invoices", "RefMatch"),
+            key
+        );
+    }
+
+    /// A local stand-in for Google's token and Sheets endpoints. Each token exchange takes
+    /// `handshake` to answer, like the OAuth round trip it stands in for; everything else
+    /// answers at once. Returns the address and a count of token exchanges.
+    async fn fake_google(handshake: std::time::Duration) -> (std::net::SocketAddr, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Response};
+        use std::convert::Infallible;
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+
+        let tokens = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&tokens);
+        let make = make_service_fn(move |_| {
+            let counter = Arc::clone(&counter);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let counter = Arc::clone(&counter);
+                    async move {
+                        let body = if req.uri().path() == "/token" {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(handshake).await;
+                            r#"{"access_token":"test-token","token_type":"Bearer","expires_in":3600}"#
+                        } else {
+                            r#"{"sheets":[{"properties":{"sheetId":0,"title":"Sheet1"}}]}"#
+                        };
+                        Ok::<_, Infallible>(Response::new(Body::from(body)))
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, tokens)
+    }
+
+    fn post() -> AnalysisResult {
+        let idea = Idea {
+            id: "8c5cd81d260a7497".into(),
+            product_name: "PayChase".into(),
+            target_user: "Freelancers".into(),
+            core_problem: "Unpaid invoices".into(),
+            mvp_features: vec!["Overdue dashboard".into()],
+            monetization: "Subscription".into(),
+            feasibility: "A few APIs".into(),
+            source_evidence: String::new(),
+            effort_estimate: "1-2 weeks".into(),
+            category: "fintech".into(),
+            scores: None,
+        };
+        AnalysisResult {
+            subreddit: "freelance".into(),
+            url: "https://www.reddit.com/r/freelance/comments/abc/unpaid_invoices".into(),
+            title: "Unpaid invoices".into(),
+            ideas_text: String::new(),
+            ideas: vec![idea],
+            score: 0,
+            num_comments: 0,
+            source: None,
+            prompt: None,
+        }
+    }
+
+    /// Per-post export latency with a client rebuilt for every post (the old behavior) against
+    /// one client shared by the run. Run with `--nocapture` to see the measured times.
+    #[tokio::test]
+    async fn shared_client_saves_a_token_exchange_per_post() {
+        const POSTS: u32 = 5;
+        let handshake = std::time::Duration::from_millis(50);
+        let (addr, tokens) = fake_google(handshake).await;
+
+        let key = include_str!("../../tests/data/service_account.json")
+            .replace("http://127.0.0.1/token", &format!("http://{}/token", addr));
+        let creds = std::env::temp_dir().join(format!("sheets-bench-{}.json", std::process::id()));
+        std::fs::write(&creds, key).unwrap();
+        let creds = creds.to_str().unwrap();
+        let client = || async {
+            let mut client = build_sheets_client(creds).await.unwrap();
+            client.base_url(format!("http://{}/", addr));
+            client
+        };
+        let result = post();
+
+        let started = std::time::Instant::now();
+        for _ in 0..POSTS {
+            let client = client().await;
+            append_ideas_batch(&client, "sheet", &result, DEFAULT_COLUMNS, false).await.unwrap();
+        }
+        let rebuilt = started.elapsed() / POSTS;
+        let rebuilt_tokens = tokens.swap(0, Ordering::SeqCst);
+
+        let started = std::time::Instant::now();
+        let shared_client = client().await;
+        for _ in 0..POSTS {
+            append_ideas_batch(&shared_client, "sheet", &result, DEFAULT_COLUMNS, false).await.unwrap();
+        }
+        let shared = started.elapsed() / POSTS;
+        let shared_tokens = tokens.load(Ordering::SeqCst);
+        let _ = std::fs::remove_file(creds);
+
+        println!("Per-post Sheets export: {:?} rebuilding the client, {:?} sharing one", rebuilt, shared);
+        assert_eq!(rebuilt_tokens, POSTS as usize);
+        assert_eq!(shared_tokens, 1);
+        assert!(rebuilt >= handshake, "{:?}", rebuilt);
+        assert!(shared < rebuilt, "{:?} vs {:?}", shared, rebuilt);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::errors::AppError;
+use crate::models::AnalysisResult;
+
+const TRELLO_API: &str = "https://api.trello.com/1";
+
+/// Create one Trello card per idea, for the first `top_n` ideas of a post.
+///
+/// Cards go to the list `list_id`, are titled with the product name, and hold
+/// the structured fields in their description. Returns the number of cards created.
+pub async fn create_cards(
+    client: &reqwest::Client,
+    key: &str,
+    token: &str,
+    list_id: &str,
+    result: &AnalysisResult,
+    top_n: usize,
+) -> Result<usize, AppError> {
+    let url = format!("{}/cards", TRELLO_API);
+    // Trello accepts credentials in an OAuth header, which keeps them out of request URLs
+    let auth = format!(
+        "OAuth oauth_consumer_key=\"{}\", oauth_token=\"{}\"",
+        key, token
+    );
+    let mut created = 0;
+
+    for idea in result.ideas.iter().take(top_n) {
+        let payload = serde_json::json!({
+            "idList": list_id,
+            "name": idea.product_name,
+            "desc": super::idea_body(idea, result),
+            "urlSource": result.url,
+            "pos": "bottom",
+        });
+
+        let res = client
+            .post(&url)
+            .header("Authorization", &auth)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| AppError::TrelloExport(format!("Failed to contact Trello: {}", e)))?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let error_text = res.text().await.unwrap_or_default();
+            return Err(AppError::TrelloExport(format!(
+                "Creating card '{}' failed ({}): {}",
+                idea.product_name, status, error_text
+            )));
+        }
+        created += 1;
+    }
+
+    Ok(created)
+}
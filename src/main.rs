@@ -1,3 +1,4 @@
+mod analysis;
 mod cli;
 mod config;
 mod errors;
@@ -7,109 +8,424 @@ mod output;
 mod services;
 mod utils;
 
-use clap::Parser;
-use cli::{Cli, Command};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::Instant;
+
+use clap::{CommandFactory, FromArgMatches};
+use futures::stream::{self, StreamExt};
+use cli::{Cli, Command, CommentSort, ErrorFormat, GlobalArgs, IdeaOrder};
 use config::AppConfig;
+use export::postgres::PostgresClient;
+use export::sheets::SheetsClient;
 use errors::AppError;
-use models::{parse_ideas, format_ideas_text, extract_subreddit, AnalysisResult, Idea};
+use models::{
+    parse_ideas, validate_ideas, format_ideas_text, extract_subreddit, AnalysisResult, Idea,
+    SourceDiscussion,
+};
+use models::RedditPost;
+use services::gemini::{GeminiClient, IdeaGenerator, PromptOptions, TopUp};
+use services::mock::Fixtures;
+use services::reddit::{CommentLimits, HttpReddit, ListedPost, RedditClient};
+use utils::profile::{self, Stage};
+
+/// Extra Gemini calls allowed per post to reach `--min-ideas`.
+const MAX_TOP_UPS: usize = 2;
+
+/// Minimum comment score under `--high-signal`, unless `--comments-min-score` overrides it.
+const HIGH_SIGNAL_MIN_SCORE: i64 = 10;
+
+/// Comments per post under `--high-signal`, unless `--comments` or `DEFAULT_COMMENTS` sets it.
+/// Higher than the usual 10, since the score floor already keeps out the noise.
+const HIGH_SIGNAL_COMMENTS: usize = 20;
+
+/// When the run began, for the wall-clock time in `--save` metadata.
+static RUN_STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Sheets that already have this run's separator row. Held across the write so concurrent
+/// posts can't append ideas to a sheet ahead of its separator.
+static SEPARATED_SHEETS: LazyLock<tokio::sync::Mutex<HashSet<String>>> =
+    LazyLock::new(|| tokio::sync::Mutex::new(HashSet::new()));
+
+/// Every duplicate idea dropped this run, for `--dedup-report`.
+static DEDUP_MERGES: std::sync::Mutex<Vec<analysis::Merge>> = std::sync::Mutex::new(Vec::new());
+
+fn record_merges(merges: Vec<analysis::Merge>) {
+    if let Ok(mut all) = DEDUP_MERGES.lock() {
+        all.extend(merges);
+    }
+}
+
+/// The first export failure this run, and how many exports failed in all. Exports don't
+/// stop a run, but a run with any failed export ends with exit code 5.
+static EXPORT_FAILURE: std::sync::Mutex<Option<AppError>> = std::sync::Mutex::new(None);
+static EXPORTS_FAILED: AtomicUsize = AtomicUsize::new(0);
+
+fn record_export_failure(error: AppError) {
+    EXPORTS_FAILED.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut first) = EXPORT_FAILURE.lock() {
+        first.get_or_insert(error);
+    }
+}
+
+/// The run's first export failure, if any, so it reaches `main` as the exit code.
+fn export_outcome() -> Result<(), AppError> {
+    let Some(error) = EXPORT_FAILURE.lock().ok().and_then(|mut first| first.take()) else {
+        return Ok(());
+    };
+    let failed = EXPORTS_FAILED.load(Ordering::Relaxed);
+    if failed > 1 {
+        eprintln!("⚠️  {} exports failed this run; the first is reported below", failed);
+    }
+    Err(error)
+}
+
+/// Posts analyzed, ideas generated, and posts failed this run, for `--log-run`.
+static POSTS_ANALYZED: AtomicUsize = AtomicUsize::new(0);
+static IDEAS_GENERATED: AtomicUsize = AtomicUsize::new(0);
+static POSTS_FAILED: AtomicUsize = AtomicUsize::new(0);
+
+/// Count a post's outcome toward the run totals. Skipped posts count as neither.
+fn tally(outcome: &Result<AnalysisResult, AppError>) {
+    match outcome {
+        Ok(result) => {
+            POSTS_ANALYZED.fetch_add(1, Ordering::Relaxed);
+            IDEAS_GENERATED.fetch_add(result.ideas.len(), Ordering::Relaxed);
+        }
+        Err(AppError::Skipped(_)) => {}
+        Err(_) => {
+            POSTS_FAILED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-    let config = AppConfig::load();
+    RUN_STARTED.get_or_init(Instant::now);
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = AppConfig::load(
+        cli.global.gemini_key.clone(),
+        cli.global.sheet_id.clone(),
+        cli.global.credentials.clone(),
+        cli.global.categories.clone(),
+        cli.global.mock,
+    );
+    let default_comments = config
+        .default_comments
+        .or(cli.global.high_signal.then_some(HIGH_SIGNAL_COMMENTS));
+    cli.command
+        .apply_defaults(&matches, default_comments, config.default_limit);
+    if let Some(dir) = &config.mock_fixtures {
+        eprintln!("🧪 Mock mode: reading responses from {}/, exports disabled", dir);
+    }
+
+    if let Some(budget) = cli.global.retry_budget {
+        utils::backoff::set_retry_budget(budget);
+    }
+    if let Some(max) = cli.global.max_concurrent_requests {
+        utils::throttle::set_max_concurrent_requests(max);
+    }
+    utils::throttle::set_reddit_cooldown(cli.global.rate_limit_cooldown);
+    if cli.global.profile {
+        utils::profile::enable();
+    }
 
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(15))
         .build()
         .expect("Failed to build HTTP client");
 
-    if let Err(e) = run(cli.command, &client, &config).await {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    // One Sheets client for the whole run, so credentials are read and exchanged once
+    // and the authenticator can refresh its token on long runs
+    let sheets = if config.sheets_enabled()
+        && config.mock_fixtures.is_none()
+        && cli.command.exports()
+    {
+        let creds_path = config.google_credentials_path.as_deref().unwrap();
+        match export::sheets::build_sheets_client(creds_path).await {
+            Ok(sheets) => Some(sheets),
+            Err(e) => {
+                eprintln!("⚠️  Sheets export disabled for this run: {}", e);
+                record_export_failure(e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Likewise one Postgres connection, with the table created before the first insert
+    let postgres = if config.postgres_enabled()
+        && config.mock_fixtures.is_none()
+        && cli.command.exports()
+    {
+        let database_url = config.database_url.as_deref().unwrap();
+        match export::postgres::build_postgres_client(database_url).await {
+            Ok(postgres) => Some(postgres),
+            Err(e) => {
+                eprintln!("⚠️  Postgres export disabled for this run: {}", e);
+                record_export_failure(e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let outcome = run(
+        cli.command,
+        &cli.global,
+        &client,
+        sheets.as_ref(),
+        postgres.as_ref(),
+        &config,
+    )
+    .await;
+    if let Some(report) = utils::profile::report() {
+        // Concurrent posts overlap, so the totals can exceed the run's wall-clock time
+        eprintln!("\n⏱  Time per stage (seconds, wall clock per post):\n{}", report);
+    }
+    if cli.global.log_run {
+        log_run(&config, outcome.as_ref().err()).await;
+    }
+    if let Err(e) = outcome {
+        match cli.global.error_format {
+            ErrorFormat::Text => eprintln!("Error: {}", e),
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                serde_json::json!({
+                    "error": {
+                        "type": e.kind(),
+                        "message": e.to_string(),
+                        "exit_code": e.exit_code(),
+                    }
+                })
+            ),
+        }
+        std::process::exit(e.exit_code());
     }
 }
 
 async fn run(
     command: Command,
+    global: &GlobalArgs,
     client: &reqwest::Client,
+    sheets: Option<&SheetsClient>,
+    postgres: Option<&PostgresClient>,
     config: &AppConfig,
 ) -> Result<(), AppError> {
     match command {
         Command::Analyze {
-            url,
+            urls,
             comments,
+            comments_only,
+            comment_sort,
+            dump_raw,
+            format,
+            save,
+        } => {
+            // Validate everything up front so a typo in the last URL doesn't waste earlier calls
+            let clean_urls = urls
+                .iter()
+                .map(|u| utils::validation::validate_reddit_url(u))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut pipeline = Pipeline::new(client, sheets, postgres, config, global, comments, comments_only);
+            if comment_sort.is_some() {
+                pipeline.limits.sort = comment_sort;
+            }
+            let mut results = Vec::new();
+            for (i, clean_url) in clean_urls.iter().enumerate() {
+                // Posts named on the command line are analyzed even if already exported
+                let subreddit = extract_subreddit(clean_url);
+                if clean_urls.len() > 1 {
+                    eprintln!("Processing: {}", clean_url);
+                }
+                pipeline.dump_raw = dump_raw.as_deref().map(|path| match clean_urls.len() {
+                    1 => path.to_string(),
+                    _ => numbered_path(path, i + 1),
+                });
+                match process_post(&pipeline, &subreddit, clean_url).await {
+                    Ok(result) => results.push(result),
+                    Err(AppError::Skipped(msg)) => eprintln!("⏭️  {}", msg),
+                    Err(e) => return Err(e),
+                }
+            }
+            emit(&results, &format, save.as_deref(), global)?;
+        }
+        Command::Batch {
+            file,
+            concurrency,
             format,
             save,
         } => {
-            let clean_url = utils::validation::validate_reddit_url(&url)?;
-            let subreddit = extract_subreddit(&clean_url);
-            let post = services::reddit::fetch_reddit_post(client, &clean_url, comments).await?;
-            let raw_ideas = services::gemini::generate_ideas(client, &config.gemini_api_key, &post).await?;
-            let ideas = parse_ideas(&raw_ideas);
-            let ideas_text = if ideas.is_empty() { raw_ideas.clone() } else { format_ideas_text(&ideas) };
-
-            export_to_sheets(config, &subreddit, &post.url, &post.title, &ideas).await;
-
-            let results = vec![AnalysisResult {
-                url: post.url,
-                title: post.title,
-                ideas_text,
-                ideas,
-            }];
-            emit(&results, &format, save.as_deref())?;
-        }
-        Command::Batch { file, format, save } => {
             let content = std::fs::read_to_string(&file)
                 .map_err(|e| AppError::Io(format!("Failed to read {}: {}", file, e)))?;
-            let mut results = Vec::new();
+            let comments = config
+                .default_comments
+                .or(global.high_signal.then_some(HIGH_SIGNAL_COMMENTS))
+                .unwrap_or(10);
+            let mut pipeline = Pipeline::new(client, sheets, postgres, config, global, comments, false);
+            let concurrency = concurrency.max(1);
+            if concurrency > 1 {
+                // Several spinners on one terminal line would overwrite each other
+                pipeline.prompt.show_progress = false;
+            }
+
+            let mut failed_posts: usize = 0;
+            let mut timed_out: usize = 0;
+            let mut skipped_posts: usize = 0;
+            let mut jobs = Vec::new();
             for line in content.lines() {
                 let line = line.trim();
                 if line.is_empty() || line.starts_with('#') {
                     continue;
                 }
-                let clean_url = utils::validation::validate_reddit_url(line)?;
-                let subreddit = extract_subreddit(&clean_url);
-                eprintln!("Processing: {}", clean_url);
-                let post =
-                    services::reddit::fetch_reddit_post(client, &clean_url, 10).await?;
-                let raw_ideas = services::gemini::generate_ideas(client, &config.gemini_api_key, &post).await?;
-                let ideas = parse_ideas(&raw_ideas);
-                let ideas_text = if ideas.is_empty() { raw_ideas.clone() } else { format_ideas_text(&ideas) };
-
-                export_to_sheets(config, &subreddit, &post.url, &post.title, &ideas).await;
-
-                results.push(AnalysisResult {
-                    url: post.url,
-                    title: post.title,
-                    ideas_text,
-                    ideas,
-                });
+                match utils::validation::validate_reddit_url(line) {
+                    Ok(clean_url) => {
+                        if !pipeline.already_exported(&clean_url).await {
+                            jobs.push((jobs.len(), clean_url));
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("⚠️  Skipping invalid line: {}", e);
+                        failed_posts += 1;
+                    }
+                }
             }
-            emit(&results, &format, save.as_deref())?;
+
+            let pipeline = &pipeline;
+            let delay = std::time::Duration::from_millis(global.delay_ms);
+            let started = tokio::time::Instant::now();
+            let mut indexed: Vec<(usize, AnalysisResult)> = Vec::new();
+            let total_jobs = jobs.len();
+            let mut finished: usize = 0;
+            let mut skipped: usize = 0;
+            let mut breaker = Breaker::new(global.max_consecutive_failures);
+            let mut outcomes = stream::iter(jobs)
+                .map(|(index, clean_url)| async move {
+                    // Space out start times evenly, however many posts run at once
+                    tokio::time::sleep_until(started + delay * index as u32).await;
+                    let subreddit = extract_subreddit(&clean_url);
+                    eprintln!("Processing: {}", clean_url);
+                    let outcome = process_post(pipeline, &subreddit, &clean_url).await;
+                    (index, clean_url, outcome)
+                })
+                .buffer_unordered(concurrency);
+
+            while let Some((index, clean_url, outcome)) = outcomes.next().await {
+                finished += 1;
+                // A post skipped on purpose isn't a failure, so it doesn't feed the breaker
+                let ok = matches!(outcome, Ok(_) | Err(AppError::Skipped(_)));
+                match outcome {
+                    Ok(r) => indexed.push((index, r)),
+                    Err(e @ AppError::Timeout(_)) => {
+                        eprintln!("⏱️  {}", e);
+                        timed_out += 1;
+                    }
+                    Err(e @ AppError::Skipped(_)) => {
+                        eprintln!("⏭️  {}", e);
+                        skipped_posts += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("⚠️  Failed to process {}: {}", clean_url, e);
+                        failed_posts += 1;
+                    }
+                }
+                if breaker.record(ok) {
+                    // Dropping the stream cancels the posts still in flight
+                    skipped = total_jobs - finished;
+                    eprintln!(
+                        "\n🔌 {} posts failed in a row (--max-consecutive-failures), stopping with {} post(s) left",
+                        breaker.streak, skipped
+                    );
+                    break;
+                }
+            }
+
+            // Restore input order, since posts finish in whatever order the network allows
+            indexed.sort_by_key(|(index, _)| *index);
+            let results: Vec<AnalysisResult> = indexed.into_iter().map(|(_, r)| r).collect();
+
+            emit(&results, &format, save.as_deref(), global)?;
+
+            let total_ideas = results.iter().map(|r| r.ideas.len()).sum();
+            print_summary(
+                &[
+                    ("Posts analyzed", results.len()),
+                    ("Ideas generated", total_ideas),
+                    ("Posts failed", failed_posts),
+                    ("Posts timed out", timed_out),
+                    ("Posts skipped", skipped_posts),
+                    ("Posts skipped by circuit breaker", skipped),
+                ],
+                &results,
+            );
         }
         Command::Subreddit {
             name,
-            limit,
+            fetch_limit,
+            analyze_limit,
+            sample,
+            seed,
             comments,
+            comments_only,
+            aggregate,
+            self_only,
             format,
             save,
         } => {
-            let results = process_subreddit(client, config, &name, limit, comments).await?;
-            emit(&results, &format, save.as_deref())?;
+            let name = utils::validation::validate_subreddit_name(&name)?;
+            let mut pipeline = Pipeline::new(client, sheets, postgres, config, global, comments, comments_only);
+            pipeline.self_only = self_only;
+            let selection = match sample {
+                Some(count) => {
+                    let seed = seed.unwrap_or_else(utils::sampling::clock_seed);
+                    eprintln!("Sampling {} posts with --seed {}", count, seed);
+                    Selection::Sample { count, seed }
+                }
+                None => Selection::First(analyze_limit),
+            };
+            if aggregate {
+                pipeline.prompt.aggregate = true;
+                let result = process_aggregate(&pipeline, &name, fetch_limit, &selection).await?;
+                emit(&[result], &format, save.as_deref(), global)?;
+            } else {
+                let (results, skipped_posts) = process_subreddit(&pipeline, &name, fetch_limit, &selection).await?;
+                emit(&results, &format, save.as_deref(), global)?;
+
+                let total_ideas = results.iter().map(|r| r.ideas.len()).sum();
+                print_summary(
+                    &[
+                        ("Posts analyzed", results.len()),
+                        ("Ideas generated", total_ideas),
+                        ("Posts skipped", skipped_posts),
+                    ],
+                    &results,
+                );
+            }
         }
         Command::Multi {
             subreddits,
             limit,
             comments,
+            comments_only,
             max_ideas,
+            expand,
+            self_only,
+            start_from,
             format,
             save,
         } => {
-            let sub_list: Vec<String> = subreddits
+            let mut pipeline = Pipeline::new(client, sheets, postgres, config, global, comments, comments_only);
+            pipeline.self_only = self_only;
+            // Validate every name up front so a typo late in the list fails before any fetch
+            let mut sub_list = subreddits
                 .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
+                .filter(|s| !s.trim().is_empty())
+                .map(utils::validation::validate_subreddit_name)
+                .collect::<Result<Vec<_>, _>>()?;
 
             if sub_list.is_empty() {
                 return Err(AppError::InvalidInput(
@@ -117,18 +433,43 @@ async fn run(
                 ));
             }
 
+            if expand > 0 {
+                let related = related_subreddits(&pipeline, &sub_list, expand).await;
+                if !related.is_empty() {
+                    eprintln!("🔎 Adding related subreddits: {}", related.join(", "));
+                }
+                sub_list.extend(related);
+            }
+
+            if let Some(start) = &start_from {
+                let start = &utils::validation::validate_subreddit_name(start)?;
+                let Some(pos) = sub_list.iter().position(|s| s.eq_ignore_ascii_case(start)) else {
+                    return Err(AppError::InvalidInput(format!(
+                        "--start-from r/{} is not in the subreddit list",
+                        start
+                    )));
+                };
+                if pos > 0 {
+                    eprintln!("⏩ Starting from r/{}, skipping {} subreddit(s)", sub_list[pos], pos);
+                }
+                sub_list.drain(..pos);
+            }
+
             let mut all_results = Vec::new();
             let mut total_ideas: usize = 0;
             let mut total_posts: usize = 0;
             let mut failed_posts: usize = 0;
+            let mut timed_out: usize = 0;
+            let mut skipped_posts: usize = 0;
             let mut subs_processed: usize = 0;
+            let mut subs_cut_short: usize = 0;
             let mut hit_limit = false;
 
             for sub in &sub_list {
                 eprintln!("\n📡 Scanning r/{}...", sub);
                 subs_processed += 1;
 
-                let urls = match services::reddit::fetch_subreddit_posts(client, sub, limit).await {
+                let posts = match pipeline.listing(sub, limit).await {
                     Ok(u) => u,
                     Err(e) => {
                         eprintln!("⚠️  Failed to fetch r/{}: {}", sub, e);
@@ -136,28 +477,52 @@ async fn run(
                     }
                 };
 
-                for url in &urls {
-                    eprintln!("Processing: {}", url);
-                    let result = process_post(client, config, sub, url, comments).await;
+                let mut breaker = Breaker::new(global.max_consecutive_failures);
+                for (i, listed) in posts.iter().enumerate() {
+                    if pipeline.already_exported(&listed.url).await {
+                        continue;
+                    }
+                    if i > 0 || subs_processed > 1 {
+                        pause(global.delay_ms).await;
+                    }
+                    eprintln!("Processing: {}", listed.url);
+                    let result = process_post(&pipeline, &listed.subreddit, &listed.url).await;
 
+                    let tripped = breaker.record(matches!(result, Ok(_) | Err(AppError::Skipped(_))));
                     match result {
                         Ok(r) => {
                             total_posts += 1;
                             total_ideas += r.ideas.len();
                             all_results.push(r);
 
-                            if let Some(max) = max_ideas {
-                                if total_ideas >= max {
-                                    hit_limit = true;
-                                    break;
-                                }
+                            if let Some(max) = max_ideas
+                                && total_ideas >= max
+                            {
+                                hit_limit = true;
+                                break;
                             }
                         }
+                        Err(e @ AppError::Timeout(_)) => {
+                            eprintln!("⏱️  {}", e);
+                            timed_out += 1;
+                        }
+                        Err(e @ AppError::Skipped(_)) => {
+                            eprintln!("⏭️  {}", e);
+                            skipped_posts += 1;
+                        }
                         Err(e) => {
                             eprintln!("⚠️  Failed to process post: {}", e);
                             failed_posts += 1;
                         }
                     }
+                    if tripped {
+                        eprintln!(
+                            "🔌 {} posts failed in a row (--max-consecutive-failures), skipping the rest of r/{}",
+                            breaker.streak, sub
+                        );
+                        subs_cut_short += 1;
+                        break;
+                    }
                 }
 
                 if hit_limit {
@@ -166,96 +531,1389 @@ async fn run(
                 }
             }
 
-            emit(&all_results, &format, save.as_deref())?;
+            emit(&all_results, &format, save.as_deref(), global)?;
+
+            print_summary(
+                &[
+                    ("Subreddits processed", subs_processed),
+                    ("Posts analyzed", total_posts),
+                    ("Ideas generated", total_ideas),
+                    ("Posts failed", failed_posts),
+                    ("Posts timed out", timed_out),
+                    ("Posts skipped", skipped_posts),
+                    ("Subreddits cut short by circuit breaker", subs_cut_short),
+                ],
+                &all_results,
+            );
+        }
+        Command::Merge {
+            files,
+            dedupe,
+            format,
+            save,
+        } => {
+            let paths = output::expand_paths(&files)?;
+            let mut results = Vec::new();
+            for path in &paths {
+                results.extend(output::read_json_run(path)?);
+            }
+
+            if dedupe {
+                let (kept, merges) = analysis::dedupe_runs(results);
+                eprintln!("Dropped {} repeated idea(s)", merges.len());
+                record_merges(merges);
+                results = kept;
+                for r in &mut results {
+                    r.ideas_text = format_ideas_text(&r.ideas);
+                }
+            }
 
-            eprintln!("\n────────────────────────────────────────");
-            eprintln!("Scan complete.\n");
-            eprintln!("Subreddits processed: {}", subs_processed);
-            eprintln!("Posts analyzed: {}", total_posts);
-            eprintln!("Ideas generated: {}", total_ideas);
-            eprintln!("Posts failed: {}", failed_posts);
-            eprintln!("────────────────────────────────────────");
+            let total_ideas: usize = results.iter().map(|r| r.ideas.len()).sum();
+            emit(&results, &format, save.as_deref(), global)?;
+            eprintln!(
+                "Merged {} run(s): {} posts, {} ideas",
+                paths.len(),
+                results.len(),
+                total_ideas
+            );
+        }
+        Command::Diff {
+            old,
+            new,
+            format,
+            save,
+        } => {
+            let old_results = output::read_json_run(&old)?;
+            let new_results = output::read_json_run(&new)?;
+            let (mut fresh, merges) = analysis::diff_runs(&old_results, new_results);
+            let repeated = merges.len();
+            record_merges(merges);
+            for r in &mut fresh {
+                r.ideas_text = format_ideas_text(&r.ideas);
+            }
+
+            let new_ideas: usize = fresh.iter().map(|r| r.ideas.len()).sum();
+            emit(&fresh, &format, save.as_deref(), global)?;
+            eprintln!("New ideas: {}, repeated from {}: {}", new_ideas, old, repeated);
+        }
+        Command::Export { file } => {
+            if config.mock_fixtures.is_some() {
+                return Err(AppError::InvalidInput(
+                    "Exports are disabled in mock mode".into(),
+                ));
+            }
+
+            let results = output::read_json_run(&file)?;
+            if let Some(r) = results.iter().find(|r| r.subreddit.is_empty()) {
+                return Err(AppError::InvalidInput(format!(
+                    "{} has a result with no subreddit ({}); it cannot be routed to an exporter",
+                    file, r.url
+                )));
+            }
+
+            let (mut posts, mut ideas, mut failed) = (0, 0, 0);
+            for result in &results {
+                eprintln!("Exporting {} idea(s) from {}", result.ideas.len(), result.url);
+                if export_result(client, sheets, postgres, config, global, result).await == 0 {
+                    posts += 1;
+                    ideas += result.ideas.len();
+                } else {
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                // The first export error still ends the command with exit code 5
+                eprintln!(
+                    "Exported {} post(s), {} idea(s) from {}; {} post(s) had export failures",
+                    posts, ideas, file, failed
+                );
+            } else {
+                eprintln!("Exported {} post(s), {} idea(s) from {}", posts, ideas, file);
+            }
+        }
+        Command::Models => {
+            if config.mock_fixtures.is_some() {
+                return Err(AppError::InvalidInput(
+                    "The models command queries the Gemini API and is not available in mock mode".into(),
+                ));
+            }
+
+            let models = services::gemini::list_models(client, &config.gemini_api_key).await?;
+            for model in &models {
+                let marker = if services::gemini::MODELS.contains(&model.name.as_str()) {
+                    " (fallback chain)"
+                } else {
+                    ""
+                };
+                println!("{}{}: {}", model.name, marker, model.methods.join(", "));
+            }
+
+            println!("\nFallback chain:");
+            for name in services::gemini::MODELS {
+                match models.iter().find(|m| m.name == *name) {
+                    Some(m) if m.supports_generate() => println!("  ✅ {}", name),
+                    Some(_) => println!("  ❌ {} (does not support generateContent)", name),
+                    None => println!("  ❌ {} (not available to this key)", name),
+                }
+            }
         }
     }
-    Ok(())
+    export_outcome()
+}
+
+/// Discover up to `max` subreddits related to `seeds` (one level deep), skipping ones already listed.
+/// Search results are taken round-robin across seeds so every seed contributes.
+async fn related_subreddits(pipeline: &Pipeline<'_>, seeds: &[String], max: usize) -> Vec<String> {
+    let mut per_seed = Vec::new();
+    for seed in seeds {
+        // Ask for extra, since the seed itself and overlaps are filtered out
+        match pipeline.reddit.search_subreddits(seed, max + seeds.len()).await {
+            Ok(names) => per_seed.push(names),
+            Err(e) => eprintln!("⚠️  Could not find subreddits related to r/{}: {}", seed, e),
+        }
+    }
+
+    let mut related: Vec<String> = Vec::new();
+    let longest = per_seed.iter().map(Vec::len).max().unwrap_or(0);
+    for i in 0..longest {
+        for names in &per_seed {
+            let Some(name) = names.get(i) else {
+                continue;
+            };
+            let known = seeds.iter().chain(&related).any(|s| s.eq_ignore_ascii_case(name));
+            if !known && related.len() < max {
+                related.push(name.clone());
+            }
+        }
+    }
+    related
+}
+
+/// Sleep between posts when `--delay-ms` is set.
+async fn pause(delay_ms: u64) {
+    if delay_ms > 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+    }
+}
+
+/// Print an end-of-run summary with counts and the top keywords across all ideas.
+fn print_summary(counts: &[(&str, usize)], results: &[AnalysisResult]) {
+    eprintln!("\n────────────────────────────────────────");
+    eprintln!("Scan complete.\n");
+    for (label, count) in counts {
+        eprintln!("{}: {}", label, count);
+    }
+
+    let all_ideas: Vec<Idea> = results.iter().flat_map(|r| r.ideas.clone()).collect();
+    let keywords = analysis::top_keywords(&all_ideas, 10);
+    if !keywords.is_empty() {
+        let list: Vec<String> = keywords
+            .iter()
+            .map(|(word, count)| format!("{} ({})", word, count))
+            .collect();
+        eprintln!("Top keywords: {}", list.join(", "));
+    }
+    eprintln!("────────────────────────────────────────");
+}
+
+/// Counts posts failing in a row, for `--max-consecutive-failures`.
+struct Breaker {
+    limit: Option<usize>,
+    streak: usize,
+}
+
+impl Breaker {
+    fn new(limit: Option<usize>) -> Self {
+        Breaker { limit, streak: 0 }
+    }
+
+    /// Record a post's outcome. Returns true once the limit of failures in a row is reached.
+    fn record(&mut self, ok: bool) -> bool {
+        self.streak = if ok { 0 } else { self.streak + 1 };
+        self.limit.is_some_and(|limit| limit > 0 && self.streak >= limit)
+    }
 }
 
-/// Process all hot posts from a single subreddit. Reused by both `subreddit` and `multi` modes.
+/// Which of a subreddit's fetched posts go on to be analyzed.
+enum Selection {
+    /// The first N in listing order, or all of them.
+    First(Option<usize>),
+    /// A seeded random sample (`--sample`), kept in listing order.
+    Sample { count: usize, seed: u64 },
+}
+
+impl Selection {
+    fn apply(&self, posts: &[ListedPost]) -> Vec<ListedPost> {
+        match *self {
+            Selection::First(limit) => posts[..limit.unwrap_or(posts.len()).min(posts.len())].to_vec(),
+            Selection::Sample { count, seed } => utils::sampling::sample(posts, count, seed),
+        }
+    }
+}
+
+/// Fetch hot posts from a single subreddit and process the ones `selection` picks.
+/// Returns the results and how many posts were skipped.
 async fn process_subreddit(
-    client: &reqwest::Client,
-    config: &AppConfig,
+    pipeline: &Pipeline<'_>,
     name: &str,
-    limit: usize,
-    comments: usize,
-) -> Result<Vec<AnalysisResult>, AppError> {
+    fetch_limit: usize,
+    selection: &Selection,
+) -> Result<(Vec<AnalysisResult>, usize), AppError> {
     eprintln!("Fetching hot posts from r/{}...", name);
-    let urls = services::reddit::fetch_subreddit_posts(client, name, limit).await?;
+    // Exported posts are dropped first so they don't use up --analyze-limit or --sample slots
+    let mut fetched = Vec::new();
+    for listed in pipeline.listing(name, fetch_limit).await? {
+        if !pipeline.already_exported(&listed.url).await {
+            fetched.push(listed);
+        }
+    }
+    let posts = selection.apply(&fetched);
+    if posts.len() < fetched.len() {
+        eprintln!("Analyzing {} of {} fetched posts", posts.len(), fetched.len());
+    }
     let mut results = Vec::new();
+    let mut skipped = 0;
 
-    for url in &urls {
-        eprintln!("Processing: {}", url);
-        let result = process_post(client, config, name, url, comments).await?;
-        results.push(result);
+    for (i, listed) in posts.iter().enumerate() {
+        if i > 0 {
+            pause(pipeline.global.delay_ms).await;
+        }
+        eprintln!("Processing: {}", listed.url);
+        match process_post(pipeline, &listed.subreddit, &listed.url).await {
+            Ok(result) => results.push(result),
+            Err(AppError::Skipped(msg)) => {
+                eprintln!("⏭️  {}", msg);
+                skipped += 1;
+            }
+            Err(e) => return Err(e),
+        }
     }
 
-    Ok(results)
+    Ok((results, skipped))
+}
+
+/// Fetch several hot posts and generate one set of ideas from all of them together.
+async fn process_aggregate(
+    pipeline: &Pipeline<'_>,
+    name: &str,
+    fetch_limit: usize,
+    selection: &Selection,
+) -> Result<AnalysisResult, AppError> {
+    let work = async {
+        eprintln!("Fetching hot posts from r/{}...", name);
+        let listed = selection.apply(&pipeline.listing(name, fetch_limit).await?);
+
+        let mut posts = Vec::new();
+        for (i, entry) in listed.iter().enumerate() {
+            if i > 0 {
+                pause(pipeline.global.delay_ms).await;
+            }
+            eprintln!("Fetching: {}", entry.url);
+            match pipeline.reddit.fetch_post(&entry.url, &pipeline.limits).await {
+                Ok(post) => match off_language(pipeline.global, &post) {
+                    Some(lang) => eprintln!("⏭️  Skipped {}: looks like {} (--lang-filter)", entry.url, lang),
+                    None => posts.push(post),
+                },
+                Err(e) => eprintln!("⚠️  Skipping {}: {}", entry.url, e),
+            }
+        }
+        if posts.is_empty() {
+            return Err(AppError::ExternalService(format!(
+                "No posts from r/{} could be fetched to aggregate",
+                name
+            )));
+        }
+
+        let fetched = posts.len();
+        let combined = combine_posts(name, posts);
+        eprintln!("Generating ideas across {} combined threads...", fetched);
+        analyze_post(pipeline, name, combined).await
+    };
+    let outcome = with_post_timeout(pipeline.global, &format!("r/{} (--aggregate)", name), work).await;
+    tally(&outcome);
+    if let Ok(result) = &outcome {
+        export(pipeline, result).await;
+    }
+    outcome
 }
 
-/// Process a single Reddit post: fetch, generate ideas, parse, and export to Sheets.
+/// Characters of thread text (bodies plus comments) allowed in one aggregate prompt.
+const AGGREGATE_CHAR_BUDGET: usize = 60_000;
+
+/// Merge posts into one, thread by thread, until the context budget runs out.
+/// Threads that don't fit are left out and the omission is noted in the body.
+fn combine_posts(subreddit: &str, posts: Vec<RedditPost>) -> RedditPost {
+    let total = posts.len();
+    let mut body = String::new();
+    let mut comments = Vec::new();
+    let mut used = 0;
+    let mut included = 0;
+    let mut score = 0;
+    let mut num_comments = 0;
+
+    for post in posts {
+        let size = post.title.chars().count()
+            + post.body.chars().count()
+            + post.comments.iter().map(|c| c.body.chars().count()).sum::<usize>();
+        // Always keep the first thread, even if it alone is over budget
+        if included > 0 && used + size > AGGREGATE_CHAR_BUDGET {
+            break;
+        }
+        used += size;
+        included += 1;
+        body.push_str(&format!(
+            "--- Thread {}: {} ({})\n{}\n\n",
+            included, post.title, post.url, post.body
+        ));
+        comments.extend(post.comments);
+        score += post.score;
+        num_comments += post.num_comments;
+    }
+
+    if included < total {
+        eprintln!(
+            "⚠️  Context budget reached: combined {} of {} threads",
+            included, total
+        );
+        body.push_str(&format!(
+            "[{} more thread(s) omitted to stay within the context budget]\n",
+            total - included
+        ));
+    }
+
+    RedditPost {
+        url: format!("https://www.reddit.com/r/{}/hot", subreddit),
+        title: format!("{} hot threads from r/{}", included, subreddit),
+        body,
+        comments,
+        image_url: None,
+        score,
+        num_comments,
+        focused_comment: None,
+    }
+}
+
+/// Split a long body into pieces of at most `max_chars`, breaking between paragraphs where
+/// possible and mid-paragraph only when a single paragraph is itself over the limit.
+fn chunk_body(body: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let pieces = body.split("\n\n").flat_map(|paragraph| {
+        let chars: Vec<char> = paragraph.chars().collect();
+        chars
+            .chunks(max_chars)
+            .map(|piece| piece.iter().collect::<String>())
+            .collect::<Vec<_>>()
+    });
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut used = 0;
+    for piece in pieces {
+        let size = piece.chars().count();
+        if used > 0 && used + 2 + size > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            used = 0;
+        }
+        if used > 0 {
+            current.push_str("\n\n");
+            used += 2;
+        }
+        current.push_str(&piece);
+        used += size;
+    }
+    if used > 0 {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Under `--chunk-long-posts`, one post per chunk of an over-long body, each titled with its
+/// part number. Comments and the image go with the first part only. Empty when the body fits.
+fn chunk_post(global: &GlobalArgs, prompt: &PromptOptions, post: &RedditPost) -> Vec<RedditPost> {
+    // --comments-only leaves the body out of the prompt, so there is nothing to split
+    if !global.chunk_long_posts || prompt.comments_only {
+        return Vec::new();
+    }
+    let chunks = chunk_body(&post.body, global.chunk_size);
+    if chunks.len() < 2 {
+        return Vec::new();
+    }
+    let total = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| RedditPost {
+            url: post.url.clone(),
+            title: format!("{} (part {} of {})", post.title, i + 1, total),
+            body,
+            comments: if i == 0 { post.comments.clone() } else { Vec::new() },
+            image_url: if i == 0 { post.image_url.clone() } else { None },
+            score: post.score,
+            num_comments: post.num_comments,
+            focused_comment: if i == 0 { post.focused_comment.clone() } else { None },
+        })
+        .collect()
+}
+
+/// Fetch and parse a post, first saving Reddit's raw response if `--dump-raw` asked for it.
+async fn fetch_post(pipeline: &Pipeline<'_>, url: &str) -> Result<RedditPost, AppError> {
+    let Some(path) = &pipeline.dump_raw else {
+        return pipeline.reddit.fetch_post(url, &pipeline.limits).await;
+    };
+    let text = pipeline.reddit.fetch_post_json(url, &pipeline.limits).await?;
+    // Saved before parsing, so a response that fails to parse can still be inspected
+    std::fs::write(path, &text)
+        .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path, e)))?;
+    eprintln!("Raw Reddit response saved to {}", path);
+    services::reddit::parse_post(url, &text, &pipeline.limits)
+}
+
+/// `path` with `-n` added before its extension: `post.json` becomes `post-2.json`.
+fn numbered_path(path: &str, n: usize) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, n, ext),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Process a single Reddit post: fetch, generate ideas, parse, and run the configured exports.
 async fn process_post(
-    client: &reqwest::Client,
-    config: &AppConfig,
+    pipeline: &Pipeline<'_>,
     subreddit: &str,
     url: &str,
-    comments: usize,
 ) -> Result<AnalysisResult, AppError> {
-    let post = services::reddit::fetch_reddit_post(client, url, comments).await?;
-    let raw_ideas = services::gemini::generate_ideas(client, &config.gemini_api_key, &post).await?;
-    let ideas = parse_ideas(&raw_ideas);
-    let ideas_text = if ideas.is_empty() { raw_ideas.clone() } else { format_ideas_text(&ideas) };
+    let work = async {
+        let post = profile::timed(url, Stage::Fetch, fetch_post(pipeline, url)).await?;
+        if let Some(lang) = off_language(pipeline.global, &post) {
+            return Err(AppError::Skipped(format!(
+                "Skipped {}: looks like {} (--lang-filter)",
+                url, lang
+            )));
+        }
+        analyze_post(pipeline, subreddit, post).await
+    };
+    let outcome = with_post_timeout(pipeline.global, url, work).await;
+    tally(&outcome);
+    if let Ok(result) = &outcome {
+        export(pipeline, result).await;
+    }
+    outcome
+}
+
+/// Run `work` under `--post-timeout`. Bounds fetching and generation, however many retries
+/// and model fallbacks pile up inside; exports run afterwards, so a slow exporter can't cancel
+/// a post whose ideas are already in hand.
+async fn with_post_timeout(
+    global: &GlobalArgs,
+    label: &str,
+    work: impl Future<Output = Result<AnalysisResult, AppError>>,
+) -> Result<AnalysisResult, AppError> {
+    let limit = global.post_timeout;
+    if limit == 0 {
+        return work.await;
+    }
+    tokio::time::timeout(std::time::Duration::from_secs(limit), work)
+        .await
+        .unwrap_or_else(|_| {
+            Err(AppError::Timeout(format!(
+                "{} took longer than {}s (--post-timeout) and was cancelled",
+                label, limit
+            )))
+        })
+}
+
+/// Under `--lang-filter`, the detected language of a post that isn't in the filter.
+/// Posts too short or mixed to call are let through.
+fn off_language(global: &GlobalArgs, post: &RedditPost) -> Option<&'static str> {
+    if global.lang_filter.is_empty() {
+        return None;
+    }
+    let detected = utils::language::detect(&format!("{}\n{}", post.title, post.body))?;
+    (!global.lang_filter.contains(&detected)).then_some(detected)
+}
+
+/// Generate and parse ideas for an already-fetched post. Exports are left to the caller.
+async fn analyze_post(
+    pipeline: &Pipeline<'_>,
+    subreddit: &str,
+    post: RedditPost,
+) -> Result<AnalysisResult, AppError> {
+    let Pipeline {
+        global,
+        prompt,
+        generator,
+        ..
+    } = pipeline;
+
+    let with_community;
+    let prompt = match pipeline.community(subreddit).await {
+        Some(community) => {
+            with_community = PromptOptions {
+                community: Some(community),
+                ..prompt.clone()
+            };
+            &with_community
+        }
+        None => prompt,
+    };
+
+    let url = post.url.clone();
+    let parts = chunk_post(global, prompt, &post);
+    if !parts.is_empty() {
+        eprintln!(
+            "📚 Body is {} characters, analyzing it in {} parts (--chunk-long-posts)",
+            post.body.chars().count(),
+            parts.len()
+        );
+    }
+    // Retries and top-ups work from the first part; the other parts are added further down
+    let first = parts.first().unwrap_or(&post);
+    let mut reply = profile::timed(&url, Stage::Gemini, generator.generate(first, prompt)).await?;
+
+    // Debugging path: show exactly what Gemini said, with no parsing and no exports
+    if global.raw_ideas {
+        return Ok(AnalysisResult {
+            subreddit: subreddit.to_string(),
+            url: post.url,
+            title: post.title,
+            ideas_text: reply.text,
+            ideas: Vec::new(),
+            score: post.score,
+            num_comments: post.num_comments,
+            source: None,
+            prompt: global.include_prompt.then_some(reply.prompt),
+        });
+    }
+
+    let mut ideas = profile::measure(&url, Stage::Parse, || parse_ideas(&reply.text));
+
+    if ideas.is_empty() && global.escalate_on_parse_fail {
+        let answered = reply.model.as_deref().unwrap_or("the model").to_string();
+        match services::gemini::escalation_model(reply.model.as_deref(), global.escalation_model.as_deref()) {
+            Some(model) => {
+                eprintln!("⚠️  No ideas parsed from {}'s response, escalating to {}...", answered, model);
+                let escalated = PromptOptions {
+                    model: Some(model),
+                    ..prompt.clone()
+                };
+                reply = profile::timed(&url, Stage::Gemini, generator.generate(first, &escalated)).await?;
+                ideas = profile::measure(&url, Stage::Parse, || parse_ideas(&reply.text));
+            }
+            None => eprintln!(
+                "⚠️  No ideas parsed from {}'s response, and no stronger model to escalate to (see --escalation-model)",
+                answered
+            ),
+        }
+    }
+
+    if ideas.is_empty() && prompt.retry_empty {
+        eprintln!("⚠️  No ideas parsed from response, re-prompting in strict JSON mode...");
+        let strict = PromptOptions {
+            strict_json: true,
+            ..prompt.clone()
+        };
+        reply = profile::timed(&url, Stage::Gemini, generator.generate(first, &strict)).await?;
+        ideas = profile::measure(&url, Stage::Parse, || parse_ideas(&reply.text));
+    }
+
+    // Ideas repeated across parts are merged by the de-duplication below
+    for (i, part) in parts.iter().enumerate().skip(1) {
+        match profile::timed(&url, Stage::Gemini, generator.generate(part, prompt)).await {
+            Ok(r) => ideas.extend(profile::measure(&url, Stage::Parse, || parse_ideas(&r.text))),
+            Err(e) => eprintln!("⚠️  Part {} of {} failed (continuing): {}", i + 1, parts.len(), e),
+        }
+    }
+
+    let (mut ideas, merges) = profile::measure(&url, Stage::Parse, || {
+        check_ideas(ideas, global.strict).map(|ideas| analysis::dedupe_ideas(ideas, &url))
+    })?;
+    if !merges.is_empty() {
+        eprintln!("⚠️  Dropped {} near-duplicate idea(s) within this post", merges.len());
+        record_merges(merges);
+    }
+
+    if let Some(min) = global.min_ideas {
+        let mut attempts = 0;
+        while !ideas.is_empty() && ideas.len() < min && attempts < MAX_TOP_UPS {
+            attempts += 1;
+            let missing = min - ideas.len();
+            eprintln!("⚠️  Got {} of {} ideas, asking Gemini for {} more...", ideas.len(), min, missing);
+            let top_up = PromptOptions {
+                top_up: Some(TopUp {
+                    count: missing,
+                    existing: ideas.iter().map(|i| i.product_name.clone()).collect(),
+                }),
+                ..prompt.clone()
+            };
+            let raw = profile::timed(&url, Stage::Gemini, generator.generate(first, &top_up))
+                .await?
+                .text;
+            let parsed = profile::measure(&url, Stage::Parse, || parse_ideas(&raw));
+            for idea in check_ideas(parsed, global.strict)? {
+                let earlier = ideas.iter().map(|i| (i, url.as_str()));
+                match analysis::find_merge(earlier, &idea, &url, "top-up") {
+                    Some(merge) => record_merges(vec![merge]),
+                    None => ideas.push(idea),
+                }
+            }
+        }
+        if ideas.len() < min {
+            eprintln!("⚠️  Only {} of {} ideas after {} top-up(s)", ideas.len(), min, attempts);
+        }
+    }
+
+    if global.score_ideas && !ideas.is_empty() {
+        match profile::timed(&url, Stage::Gemini, generator.score(&post, &ideas, prompt)).await {
+            Ok(raw) => {
+                let scored = profile::measure(&url, Stage::Parse, || models::parse_scores(&raw, &mut ideas));
+                if scored < ideas.len() {
+                    eprintln!("⚠️  Gemini rated {} of {} ideas; the rest stay unscored", scored, ideas.len());
+                }
+            }
+            Err(e) => eprintln!("⚠️  Idea scoring failed (continuing unscored): {}", e),
+        }
+        if let Some(min) = global.min_idea_score {
+            // Unscored ideas are kept, since a failed scoring call says nothing about them
+            let before = ideas.len();
+            ideas.retain(|idea| idea.scores.is_none_or(|s| s.average() >= min));
+            if ideas.len() < before {
+                eprintln!("Dropped {} idea(s) rated below {} (--min-idea-score)", before - ideas.len(), min);
+            }
+        }
+    }
+
+    // Both sorts are stable, so ties keep the model's order
+    match global.sort_ideas {
+        IdeaOrder::Model => {}
+        IdeaOrder::Effort => ideas.sort_by_key(|idea| models::effort_rank(&idea.effort_estimate)),
+        IdeaOrder::Score => {
+            let rating = |idea: &Idea| idea.scores.map_or(0.0, |s| s.average());
+            ideas.sort_by(|a, b| rating(b).total_cmp(&rating(a)));
+        }
+    }
 
-    export_to_sheets(config, subreddit, &post.url, &post.title, &ideas).await;
+    let ideas_text = if ideas.is_empty() { reply.text } else { format_ideas_text(&ideas) };
 
-    Ok(AnalysisResult {
+    let source = global.include_source.then_some(SourceDiscussion {
+        body: post.body,
+        comments: post.comments,
+    });
+
+    let result = AnalysisResult {
+        subreddit: subreddit.to_string(),
         url: post.url,
         title: post.title,
         ideas_text,
         ideas,
-    })
+        score: post.score,
+        num_comments: post.num_comments,
+        source,
+        prompt: global.include_prompt.then_some(reply.prompt),
+    };
+
+    Ok(result)
+}
+
+/// Run the configured exports on a finished post. Mock runs and `--raw-ideas` export nothing.
+async fn export(pipeline: &Pipeline<'_>, result: &AnalysisResult) {
+    let Pipeline { client, sheets, postgres, config, global, .. } = pipeline;
+    if config.mock_fixtures.is_some() || global.raw_ideas {
+        return;
+    }
+    let export = export_result(client, *sheets, *postgres, config, global, result);
+    profile::timed(&result.url, Stage::Export, export).await;
+}
+
+/// Run every configured exporter on one post's results.
+/// Every exporter runs even if an earlier one fails. Each failure is printed and recorded
+/// for the exit code; returns how many exporters failed.
+async fn export_result(
+    client: &reqwest::Client,
+    sheets: Option<&SheetsClient>,
+    postgres: Option<&PostgresClient>,
+    config: &AppConfig,
+    global: &GlobalArgs,
+    result: &AnalysisResult,
+) -> usize {
+    let to_sheets = match sheets {
+        Some(sheets) => export_to_sheets(sheets, config, global, result).await,
+        None => Ok(()),
+    };
+    let outcomes = [
+        to_sheets,
+        export_to_postgres(postgres, result).await,
+        export_to_github(client, config, global, result).await,
+        export_to_trello(client, config, global, result).await,
+    ];
+    let mut failed = 0;
+    for e in outcomes.into_iter().filter_map(Result::err) {
+        eprintln!("⚠️  {} (continuing)", e);
+        record_export_failure(e);
+        failed += 1;
+    }
+    failed
 }
 
-/// Export ideas to Google Sheets if configured. Prints error but never crashes.
+/// Drop ideas that fail validation, or fail outright under `--strict`.
+fn check_ideas(ideas: Vec<Idea>, strict: bool) -> Result<Vec<Idea>, AppError> {
+    let (ideas, rejected) = validate_ideas(ideas);
+    if rejected > 0 {
+        if strict {
+            return Err(AppError::ExternalService(format!(
+                "{} idea(s) from Gemini failed validation (--strict)",
+                rejected
+            )));
+        }
+        eprintln!("⚠️  Dropped {} idea(s) with blank fields or no MVP features", rejected);
+    }
+    Ok(ideas)
+}
+
+/// Per-command settings shared by every post processed in a run.
+struct Pipeline<'a> {
+    client: &'a reqwest::Client,
+    /// Shared Sheets client; `None` when Sheets export is off or could not authenticate.
+    sheets: Option<&'a SheetsClient>,
+    /// Shared Postgres connection; `None` when Postgres export is off or could not connect.
+    postgres: Option<&'a PostgresClient>,
+    config: &'a AppConfig,
+    global: &'a GlobalArgs,
+    prompt: PromptOptions,
+    limits: CommentLimits,
+    /// Live API clients normally, fixtures under `--mock`; any implementation can be swapped in.
+    reddit: Box<dyn RedditClient + 'a>,
+    generator: Box<dyn IdeaGenerator + 'a>,
+    /// Subreddit descriptions fetched so far under `--with-context`, so each is fetched once.
+    descriptions: Mutex<HashMap<String, Option<String>>>,
+    /// Drop link posts from subreddit listings (`--self-only`).
+    self_only: bool,
+    /// Where to write the next fetched post's raw Reddit JSON (`analyze --dump-raw`).
+    dump_raw: Option<String>,
+    /// Post keys already in the configured sheets, read on first use unless `--force` is set.
+    exported: tokio::sync::OnceCell<HashSet<String>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Combine config and global flags with a command's own comment settings.
+    fn new(
+        client: &'a reqwest::Client,
+        sheets: Option<&'a SheetsClient>,
+        postgres: Option<&'a PostgresClient>,
+        config: &'a AppConfig,
+        global: &'a GlobalArgs,
+        max_comments: usize,
+        comments_only: bool,
+    ) -> Self {
+        // Mock mode answers both services from the same fixture directory
+        let (reddit, generator): (Box<dyn RedditClient + 'a>, Box<dyn IdeaGenerator + 'a>) =
+            match &config.mock_fixtures {
+                Some(dir) => (Box::new(Fixtures::new(dir)), Box::new(Fixtures::new(dir))),
+                None => (
+                    Box::new(HttpReddit {
+                        client,
+                        user_agent: &config.reddit_user_agent,
+                    }),
+                    Box::new(GeminiClient {
+                        client,
+                        api_key: &config.gemini_api_key,
+                    }),
+                ),
+            };
+
+        Pipeline {
+            client,
+            sheets,
+            postgres,
+            config,
+            global,
+            prompt: PromptOptions {
+                weighting: config.comment_weighting.clone(),
+                comments_only,
+                aggregate: false,
+                community: None,
+                retry_empty: global.retry_empty,
+                comment_refs: global.comment_refs,
+                comment_strategy: global.comment_strategy,
+                strict_json: false,
+                guidance: global.guidance.clone(),
+                categories: config.categories.clone(),
+                persona: global
+                    .persona
+                    .clone()
+                    .unwrap_or_else(|| services::gemini::DEFAULT_PERSONA.to_string()),
+                top_up: None,
+                timeout: Some(std::time::Duration::from_secs(global.gemini_timeout)),
+                language: Some(global.lang),
+                vision: global.vision,
+                safety_threshold: config.gemini_safety_threshold.clone(),
+                verbose: global.verbose,
+                show_progress: !global.quiet,
+                model: None,
+            },
+            limits: CommentLimits {
+                max_comments,
+                max_chars: global.comment_chars,
+                max_comment_length: global.max_comments_length,
+                sort: global.high_signal.then_some(CommentSort::Top),
+                min_score: global
+                    .comments_min_score
+                    .or(global.high_signal.then_some(HIGH_SIGNAL_MIN_SCORE)),
+                top_replies: global.with_top_replies,
+            },
+            reddit,
+            generator,
+            self_only: false,
+            dump_raw: None,
+            descriptions: Mutex::new(HashMap::new()),
+            exported: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    /// Hot posts from a subreddit, minus link posts under `--self-only`.
+    async fn listing(&self, subreddit: &str, limit: usize) -> Result<Vec<ListedPost>, AppError> {
+        let mut posts = self.reddit.fetch_listing(subreddit, limit).await?;
+        if self.self_only {
+            let listed = posts.len();
+            posts.retain(|p| p.is_self);
+            if posts.len() < listed {
+                eprintln!("⏭️  Skipped {} link post(s) in r/{}", listed - posts.len(), subreddit);
+            }
+        }
+        Ok(posts)
+    }
+
+    /// True (with a notice) if `url` is already in a configured sheet. Off under `--force`,
+    /// and when the column layout has no Post URL column to check.
+    async fn already_exported(&self, url: &str) -> bool {
+        let has_urls = self.config.sheet_columns.contains(&export::sheets::Column::PostUrl);
+        let Some(client) = self.sheets.filter(|_| has_urls && !self.global.force) else {
+            return false;
+        };
+        let exported = self
+            .exported
+            .get_or_init(|| async {
+                let mut keys = HashSet::new();
+                let sheets = self
+                    .config
+                    .google_sheet_id
+                    .iter()
+                    .chain(self.config.sheet_routes.iter().map(|(_, id)| id));
+                for sheet_id in sheets {
+                    match export::sheets::read_exported_posts(client, sheet_id, &self.config.sheet_columns).await {
+                        Ok(found) => keys.extend(found),
+                        Err(e) => eprintln!("⚠️  Could not read exported posts (continuing): {}", e),
+                    }
+                }
+                eprintln!("Found {} post(s) already exported", keys.len());
+                keys
+            })
+            .await;
+
+        let skip = exported.contains(&export::sheets::post_key(url));
+        if skip {
+            eprintln!("⏭️  Skipping {} (already exported; --force analyzes it anyway)", url);
+        }
+        skip
+    }
+
+    /// Subreddit name and description for the prompt, when `--with-context` is on and one exists.
+    async fn community(&self, subreddit: &str) -> Option<(String, String)> {
+        if !self.global.with_context || subreddit.is_empty() {
+            return None;
+        }
+        let key = subreddit.to_lowercase();
+        let cached = self.descriptions.lock().ok()?.get(&key).cloned();
+        let description = match cached {
+            Some(description) => description,
+            None => {
+                let fetched = match self.reddit.fetch_description(subreddit).await {
+                    Ok(description) => description,
+                    Err(e) => {
+                        eprintln!("⚠️  No community context for r/{} (continuing): {}", subreddit, e);
+                        None
+                    }
+                };
+                if let Ok(mut cache) = self.descriptions.lock() {
+                    cache.insert(key, fetched.clone());
+                }
+                fetched
+            }
+        };
+        description.map(|d| (subreddit.to_string(), d))
+    }
+}
+
+/// Export ideas to Google Sheets if configured.
 async fn export_to_sheets(
+    sheets: &SheetsClient,
     config: &AppConfig,
-    subreddit: &str,
-    post_url: &str,
-    post_title: &str,
-    ideas: &[Idea],
-) {
-    if !config.sheets_enabled() || ideas.is_empty() {
+    global: &GlobalArgs,
+    result: &AnalysisResult,
+) -> Result<(), AppError> {
+    if result.ideas.is_empty() {
+        return Ok(());
+    }
+
+    // No default sheet and no route for this subreddit: nothing to export to
+    let Some(sheet_id) = config.sheet_for(&result.subreddit) else {
+        return Ok(());
+    };
+
+    if config.sheets_run_separator {
+        let mut separated = SEPARATED_SHEETS.lock().await;
+        if !separated.contains(sheet_id) {
+            let label = format!(
+                "── Run {} ── {}",
+                chrono::Utc::now().format("%Y-%m-%d %H:%M UTC"),
+                output::command_line().join(" ")
+            );
+            // Not retried: a missing separator is cosmetic and shouldn't hold up the ideas
+            if let Err(e) = export::sheets::append_separator(sheets, sheet_id, &label).await {
+                eprintln!("⚠️  {} (continuing)", e);
+            }
+            separated.insert(sheet_id.to_string());
+        }
+    }
+
+    let report = export::sheets::append_ideas_batch(
+        sheets,
+        sheet_id,
+        result,
+        &config.sheet_columns,
+        global.no_duplicate_rows,
+    )
+    .await?;
+    if report.skipped > 0 {
+        eprintln!(
+            "✅ Exported {} ideas to Google Sheet ({} skipped as duplicates)",
+            report.appended, report.skipped
+        );
+    } else {
+        eprintln!("✅ Exported {} ideas to Google Sheet", report.appended);
+    }
+    Ok(())
+}
+
+/// Insert ideas into Postgres if configured.
+async fn export_to_postgres(postgres: Option<&PostgresClient>, result: &AnalysisResult) -> Result<(), AppError> {
+    let Some(postgres) = postgres.filter(|_| !result.ideas.is_empty()) else {
+        return Ok(());
+    };
+
+    let inserted = export::postgres::insert_ideas_batch(postgres, result).await?;
+    if (inserted as usize) < result.ideas.len() {
+        eprintln!(
+            "✅ Inserted {} ideas into Postgres ({} already stored)",
+            inserted,
+            result.ideas.len() - inserted as usize
+        );
+    } else {
+        eprintln!("✅ Inserted {} ideas into Postgres", inserted);
+    }
+    Ok(())
+}
+
+/// Append this run to the Postgres `runs` table (`--log-run`). Prints error but never crashes.
+async fn log_run(config: &AppConfig, error: Option<&AppError>) {
+    let Some(database_url) = config.database_url.as_deref() else {
+        eprintln!("⚠️  --log-run needs DATABASE_URL; run not logged");
         return;
+    };
+
+    let usage = services::gemini::usage();
+    let elapsed = RUN_STARTED.get().map(Instant::elapsed).unwrap_or_default();
+    let run = export::postgres::RunRecord {
+        command: output::command_line(),
+        elapsed_secs: (elapsed.as_secs_f64() * 10.0).round() / 10.0,
+        posts: POSTS_ANALYZED.load(Ordering::Relaxed) as i64,
+        ideas: IDEAS_GENERATED.load(Ordering::Relaxed) as i64,
+        posts_failed: POSTS_FAILED.load(Ordering::Relaxed) as i64,
+        total_tokens: usage.total_tokens as i64,
+        models: usage.models,
+        error: error.map(|e| e.to_string()),
+    };
+    match export::postgres::insert_run(database_url, &run).await {
+        Ok(()) => eprintln!("✅ Logged run to Postgres"),
+        Err(e) => eprintln!("⚠️  Run log failed: {}", e),
     }
+}
 
-    let sheet_id = config.google_sheet_id.as_deref().unwrap();
-    let creds_path = config.google_credentials_path.as_deref().unwrap();
+/// File the top ideas as GitHub issues if configured.
+async fn export_to_github(
+    client: &reqwest::Client,
+    config: &AppConfig,
+    global: &GlobalArgs,
+    result: &AnalysisResult,
+) -> Result<(), AppError> {
+    if !config.github_enabled() || result.ideas.is_empty() || global.github_top_n == 0 {
+        return Ok(());
+    }
+
+    let token = config.github_token.as_deref().unwrap();
+    let repo = config.github_repo.as_deref().unwrap();
+
+    let created = export::github::create_issues(client, token, repo, result, global.github_top_n).await?;
+    eprintln!("✅ Created {} GitHub issue(s) in {}", created, repo);
+    Ok(())
+}
 
-    match export::sheets::append_ideas_batch(sheet_id, creds_path, subreddit, post_url, post_title, ideas).await {
-        Ok(()) => eprintln!("✅ Exported {} ideas to Google Sheet", ideas.len()),
-        Err(e) => eprintln!("⚠️  Sheet export failed (continuing): {}", e),
+/// Create Trello cards for the top ideas if configured.
+async fn export_to_trello(
+    client: &reqwest::Client,
+    config: &AppConfig,
+    global: &GlobalArgs,
+    result: &AnalysisResult,
+) -> Result<(), AppError> {
+    if !config.trello_enabled() || result.ideas.is_empty() || global.trello_top_n == 0 {
+        return Ok(());
     }
+
+    let key = config.trello_key.as_deref().unwrap();
+    let token = config.trello_token.as_deref().unwrap();
+    let list_id = config.trello_list_id.as_deref().unwrap();
+
+    let created = export::trello::create_cards(client, key, token, list_id, result, global.trello_top_n).await?;
+    eprintln!("✅ Created {} Trello card(s)", created);
+    Ok(())
 }
 
 fn emit(
     results: &[AnalysisResult],
     format: &cli::OutputFormat,
     save: Option<&str>,
+    global: &GlobalArgs,
 ) -> Result<(), AppError> {
-    let text = output::format_results(results, format);
+    let csv = output::CsvOptions {
+        delimiter: global.csv_delimiter.char(),
+        bom: global.csv_bom,
+    };
+    let filtered;
+    let results = if global.filter_category.is_empty() {
+        results
+    } else {
+        filtered = filter_categories(results, &global.filter_category);
+        &filtered
+    };
+    let sorted;
+    let results = match global.sort_by {
+        Some(order) => {
+            sorted = output::sort_results(results, order);
+            &sorted
+        }
+        None => results,
+    };
+    let text = output::format_results(results, format, csv, global.truncate_fields);
     println!("{}", text);
-    if let Some(path) = save {
-        std::fs::write(path, &text)
-            .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path, e)))?;
-        eprintln!("Output saved to {}", path);
+    if let Some(path) = &global.dedup_report {
+        let merges = DEDUP_MERGES.lock().map(|m| m.clone()).unwrap_or_default();
+        write_output(path, &output::format_dedup_report(&merges))?;
+    }
+    let Some(template) = save else {
+        return Ok(());
+    };
+
+    let is_all = matches!(format, cli::OutputFormat::All);
+    let formats = if is_all {
+        cli::OutputFormat::CONCRETE
+    } else {
+        std::slice::from_ref(format)
+    };
+
+    // A {subreddit} placeholder splits the run into one file per subreddit
+    let groups: Vec<(&str, Vec<AnalysisResult>)> = if template.contains("{subreddit}") {
+        output::group_by_subreddit(results)
+            .into_iter()
+            .map(|(sub, group)| (sub, group.into_iter().cloned().collect()))
+            .collect()
+    } else {
+        vec![("", results.to_vec())]
+    };
+
+    // Each file written gets a sidecar; files that differ only by extension share one
+    let mut sidecars: Vec<(String, &[AnalysisResult])> = Vec::new();
+    for fmt in formats {
+        for (subreddit, group) in &groups {
+            let path = output::save_path(template, fmt, subreddit, is_all);
+            let meta_path = output::meta_path(&path);
+            if !sidecars.iter().any(|(p, _)| *p == meta_path) {
+                sidecars.push((meta_path, group));
+            }
+            if global.save_append {
+                append_output(&path, fmt, group, csv, global)?;
+                continue;
+            }
+            let mut text = output::format_results(group, fmt, csv, global.truncate_fields);
+            if csv.bom && matches!(fmt, cli::OutputFormat::Csv) {
+                text.insert(0, '\u{feff}');
+            }
+            write_output(&path, &text)?;
+        }
+    }
+
+    let elapsed = RUN_STARTED.get().map(Instant::elapsed).unwrap_or_default();
+    for (meta_path, group) in sidecars {
+        let mut meta = output::format_meta(group, services::gemini::usage(), elapsed);
+        if global.save_append {
+            // The files hold every appended run, so the sidecar keeps a record of each one too
+            let existing = std::fs::read_to_string(&meta_path).unwrap_or_default();
+            meta = output::append_meta(&existing, &meta);
+        }
+        write_output(&meta_path, &meta)?;
+    }
+    Ok(())
+}
+
+/// `--save-append`: add `results` to the file at `path` rather than replacing it. A saved JSON
+/// run is merged into one envelope and CSV gets rows under the existing header; text and
+/// markdown are appended after a divider. A missing or empty file is written as usual.
+fn append_output(
+    path: &str,
+    format: &cli::OutputFormat,
+    results: &[AnalysisResult],
+    csv: output::CsvOptions,
+    global: &GlobalArgs,
+) -> Result<(), AppError> {
+    let existing = std::fs::read_to_string(path).unwrap_or_default();
+    let fresh = output::format_results(results, format, csv, global.truncate_fields);
+    if existing.trim().is_empty() {
+        let bom = if csv.bom && matches!(format, cli::OutputFormat::Csv) { "\u{feff}" } else { "" };
+        return write_output(path, &format!("{}{}", bom, fresh));
     }
+
+    let combined = match format {
+        cli::OutputFormat::Json => {
+            let mut merged = output::read_json_run(path)?;
+            merged.extend(results.iter().cloned());
+            output::format_results(&merged, format, csv, global.truncate_fields)
+        }
+        cli::OutputFormat::Csv => {
+            let (header, rows) = fresh.split_once('\n').unwrap_or((&fresh, ""));
+            let existing_header = existing.trim_start_matches('\u{feff}').lines().next();
+            if existing_header.map(str::trim_end) != Some(header) {
+                return Err(AppError::InvalidInput(format!(
+                    "Can't append to {}: its CSV header doesn't match this run's columns or \
+                     --csv-delimiter",
+                    path
+                )));
+            }
+            format!("{}{}{}", existing, if existing.ends_with('\n') { "" } else { "\n" }, rows)
+        }
+        cli::OutputFormat::Markdown | cli::OutputFormat::MarkdownTable => {
+            format!("{}\n---\n\n{}", existing.trim_end(), fresh)
+        }
+        cli::OutputFormat::Text | cli::OutputFormat::All => format!(
+            "{}\n\n════════════════════════════════════════\n\n{}",
+            existing.trim_end(),
+            fresh
+        ),
+    };
+    write_output(path, &combined)
+}
+
+/// Keep only ideas tagged with one of `categories` (`--filter-category`). Posts left with no
+/// ideas are dropped, as are raw replies that never parsed into tagged ideas.
+fn filter_categories(results: &[AnalysisResult], categories: &[String]) -> Vec<AnalysisResult> {
+    let wanted: Vec<String> = categories.iter().map(|c| c.trim().to_lowercase()).collect();
+    results
+        .iter()
+        .filter_map(|r| {
+            let mut r = r.clone();
+            r.ideas.retain(|idea| wanted.contains(&idea.category));
+            r.ideas_text = format_ideas_text(&r.ideas);
+            (!r.ideas.is_empty()).then_some(r)
+        })
+        .collect()
+}
+
+fn write_output(path: &str, text: &str) -> Result<(), AppError> {
+    std::fs::write(path, text)
+        .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path, e)))?;
+    eprintln!("Output saved to {}", path);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use futures::future::BoxFuture;
+    use services::gemini::Reply;
+
+    const URL: &str = "https://www.reddit.com/r/freelance/comments/abc123/unpaid_invoices";
+
+    const IDEAS: &str = r#"[{
+        "product_name": "PayChase",
+        "target_user": "Freelancers with many clients",
+        "core_problem": "Unpaid invoices are spread across several tools",
+        "mvp_features": ["Import invoices", "Overdue dashboard"],
+        "monetization": "Subscription",
+        "feasibility": "A few well-documented APIs",
+        "category": "Fintech"
+    }]"#;
+
+    /// Serves one canned post for any URL.
+    struct FakeReddit;
+
+    impl RedditClient for FakeReddit {
+        fn fetch_post_json<'a>(
+            &'a self,
+            _url: &'a str,
+            _limits: &'a CommentLimits,
+        ) -> BoxFuture<'a, Result<String, AppError>> {
+            Box::pin(async { Err(AppError::ExternalService("not used".into())) })
+        }
+
+        fn fetch_post<'a>(
+            &'a self,
+            url: &'a str,
+            _limits: &'a CommentLimits,
+        ) -> BoxFuture<'a, Result<RedditPost, AppError>> {
+            Box::pin(async move {
+                Ok(RedditPost {
+                    url: url.to_string(),
+                    title: "How do you keep track of unpaid invoices?".into(),
+                    body: "I have eleven clients on four invoicing tools and chase payments by hand.".into(),
+                    comments: Vec::new(),
+                    image_url: None,
+                    score: 42,
+                    num_comments: 7,
+                    focused_comment: None,
+                })
+            })
+        }
+
+        fn fetch_listing<'a>(
+            &'a self,
+            _subreddit: &'a str,
+            _limit: usize,
+        ) -> BoxFuture<'a, Result<Vec<ListedPost>, AppError>> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+
+        fn fetch_description<'a>(
+            &'a self,
+            _subreddit: &'a str,
+        ) -> BoxFuture<'a, Result<Option<String>, AppError>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn search_subreddits<'a>(
+            &'a self,
+            _query: &'a str,
+            _limit: usize,
+        ) -> BoxFuture<'a, Result<Vec<String>, AppError>> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+    }
+
+    /// Answers with `first` until asked for strict JSON, then with `IDEAS`. Records whether
+    /// each call was strict.
+    struct FakeGenerator {
+        first: &'static str,
+        calls: std::sync::Arc<Mutex<Vec<bool>>>,
+    }
+
+    impl IdeaGenerator for FakeGenerator {
+        fn generate<'a>(
+            &'a self,
+            _post: &'a RedditPost,
+            options: &'a PromptOptions,
+        ) -> BoxFuture<'a, Result<Reply, AppError>> {
+            Box::pin(async move {
+                self.calls.lock().unwrap().push(options.strict_json);
+                let text = if options.strict_json { IDEAS } else { self.first };
+                Ok(Reply {
+                    text: text.to_string(),
+                    prompt: "prompt".into(),
+                    model: Some("fake-model".into()),
+                })
+            })
+        }
+
+        fn score<'a>(
+            &'a self,
+            _post: &'a RedditPost,
+            _ideas: &'a [Idea],
+            _options: &'a PromptOptions,
+        ) -> BoxFuture<'a, Result<String, AppError>> {
+            Box::pin(async { Ok("[]".into()) })
+        }
+    }
+
+    fn global(flags: &[&str]) -> GlobalArgs {
+        let args = ["reddit-research-engine", "-q"].iter().chain(flags).chain(&["analyze", URL]);
+        Cli::try_parse_from(args).unwrap().global
+    }
+
+    /// Mock config, so nothing is exported; the fakes replace the fixture clients.
+    fn config() -> AppConfig {
+        AppConfig::load(Some("test-key".into()), None, None, None, true)
+    }
+
+    /// A pipeline on the fakes, plus the generator's call log.
+    fn pipeline<'a>(
+        client: &'a reqwest::Client,
+        config: &'a AppConfig,
+        global: &'a GlobalArgs,
+        first_reply: &'static str,
+    ) -> (Pipeline<'a>, std::sync::Arc<Mutex<Vec<bool>>>) {
+        let calls = std::sync::Arc::default();
+        let mut pipeline = Pipeline::new(client, None, None, config, global, 10, false);
+        pipeline.reddit = Box::new(FakeReddit);
+        pipeline.generator = Box::new(FakeGenerator { first: first_reply, calls: std::sync::Arc::clone(&calls) });
+        (pipeline, calls)
+    }
+
+    #[tokio::test]
+    async fn process_post_builds_result_from_fakes() {
+        let (client, config, global) = (reqwest::Client::new(), config(), global(&[]));
+        let (pipeline, calls) = pipeline(&client, &config, &global, IDEAS);
+
+        let result = process_post(&pipeline, "freelance", URL).await.unwrap();
+
+        assert_eq!(result.subreddit, "freelance");
+        assert_eq!(result.url, URL);
+        assert_eq!(result.title, "How do you keep track of unpaid invoices?");
+        assert_eq!((result.score, result.num_comments), (42, 7));
+        assert_eq!(result.ideas.len(), 1);
+        assert_eq!(result.ideas[0].product_name, "PayChase");
+        assert_eq!(result.ideas[0].category, "fintech");
+        assert!(!result.ideas[0].id.is_empty());
+        assert!(result.ideas_text.contains("PayChase"));
+        assert!(result.prompt.is_none());
+        assert_eq!(*calls.lock().unwrap(), [false]);
+    }
+
+    #[tokio::test]
+    async fn unparseable_reply_is_retried_in_strict_json_mode() {
+        let (client, config, global) = (reqwest::Client::new(), config(), global(&["--retry-empty"]));
+        let (pipeline, calls) = pipeline(&client, &config, &global, "Here are some thoughts, but no JSON.");
+
+        let post = pipeline.reddit.fetch_post(URL, &pipeline.limits).await.unwrap();
+        let result = analyze_post(&pipeline, "freelance", post).await.unwrap();
+
+        assert_eq!(result.ideas.len(), 1);
+        assert_eq!(*calls.lock().unwrap(), [false, true]);
+    }
+
+    #[tokio::test]
+    async fn lang_filter_skips_post_before_calling_generator() {
+        let (client, config, global) = (reqwest::Client::new(), config(), global(&["--lang-filter", "de"]));
+        let (pipeline, calls) = pipeline(&client, &config, &global, IDEAS);
+
+        let outcome = process_post(&pipeline, "freelance", URL).await;
+
+        assert!(matches!(outcome, Err(AppError::Skipped(_))));
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn default_post_timeout_outlasts_the_fallback_chain() {
+        let global = global(&[]);
+        let chain = global.gemini_timeout * services::gemini::MODELS.len() as u64;
+        assert!(chain < global.post_timeout, "{}s of model timeouts vs {}s per post", chain, global.post_timeout);
+    }
+}
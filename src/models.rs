@@ -1,34 +1,194 @@
 use serde::{Deserialize, Serialize};
 
+use crate::analysis;
+
 #[derive(Serialize)]
 pub struct RedditPost {
     pub url: String,
     pub title: String,
     pub body: String,
-    pub comments: Vec<String>,
+    pub comments: Vec<Comment>,
+    /// Direct link to the post's image, for image posts.
+    pub image_url: Option<String>,
+    /// Post score (upvotes minus downvotes) when fetched.
+    pub score: i64,
+    /// Total comments on the post, not just the ones sent to Gemini.
+    pub num_comments: u64,
+    /// Id of the comment a comment permalink pointed at. `comments` is then that comment
+    /// and its replies, in thread order.
+    pub focused_comment: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Comment {
+    /// Reddit's base36 comment id (without the `t1_` prefix).
+    pub id: String,
+    pub permalink: String,
+    pub body: String,
+    pub score: i64,
+    /// Nesting level in the thread (0 = top-level).
+    pub depth: u32,
+    /// Highest-scored direct reply, when fetched with `--with-top-replies`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_reply: Option<Box<Comment>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Idea {
+    /// Content hash of the product name and core problem; see [`idea_id`].
+    #[serde(default)]
+    pub id: String,
     pub product_name: String,
     pub target_user: String,
     pub core_problem: String,
     pub mvp_features: Vec<String>,
     pub monetization: String,
     pub feasibility: String,
+    /// The part of the post or the comment the idea came from, quoted or paraphrased by the model.
+    /// Empty for runs saved before this field existed.
+    #[serde(default)]
+    pub source_evidence: String,
+    /// Rough solo build time, ideally one of [`EFFORT_LEVELS`]. Empty for older saved runs.
+    #[serde(default)]
+    pub effort_estimate: String,
+    /// Lowercase tag from the configured category list. Empty for older saved runs.
+    #[serde(default)]
+    pub category: String,
+    /// Ratings from the `--score-ideas` pass; absent when scoring was off or failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<IdeaScores>,
 }
 
-#[derive(Serialize)]
+/// 1-10 ratings a second Gemini call gives an idea under `--score-ideas`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct IdeaScores {
+    pub novelty: u8,
+    pub feasibility: u8,
+    pub market_fit: u8,
+}
+
+impl IdeaScores {
+    /// Mean of the three ratings, for sorting and `--min-idea-score`.
+    pub fn average(&self) -> f64 {
+        (self.novelty as f64 + self.feasibility as f64 + self.market_fit as f64) / 3.0
+    }
+}
+
+/// Categories offered to the model unless `--categories` or `IDEA_CATEGORIES` replace them.
+pub const DEFAULT_CATEGORIES: &[&str] = &[
+    "productivity",
+    "dev-tools",
+    "fintech",
+    "marketing",
+    "e-commerce",
+    "education",
+    "health",
+    "creator-tools",
+    "other",
+];
+
+/// Build-effort buckets the model is asked to choose from, quickest first.
+pub const EFFORT_LEVELS: &[&str] = &["weekend", "1-2 weeks", "1-3 months", "3+ months"];
+
+/// Position of an effort estimate in [`EFFORT_LEVELS`], for sorting quickest first.
+/// Free-form answers are bucketed by their unit; blank or unrecognized ones sort last.
+pub fn effort_rank(estimate: &str) -> usize {
+    let estimate = estimate.trim().to_lowercase();
+    if let Some(i) = EFFORT_LEVELS.iter().position(|level| *level == estimate) {
+        return i;
+    }
+    if estimate.contains("3+") || estimate.contains("year") {
+        3
+    } else if estimate.contains("month") {
+        2
+    } else if estimate.contains("weekend") || estimate.contains("day") || estimate.contains("hour") {
+        0
+    } else if estimate.contains("week") {
+        1
+    } else {
+        EFFORT_LEVELS.len()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct AnalysisResult {
+    /// Defaults to empty when loading runs saved before this field existed.
+    #[serde(default)]
+    pub subreddit: String,
     pub url: String,
     pub title: String,
     pub ideas_text: String,
     pub ideas: Vec<Idea>,
+    /// Post score and comment count when fetched, for `--sort-by`. Zero in older saved runs.
+    #[serde(default)]
+    pub score: i64,
+    #[serde(default)]
+    pub num_comments: u64,
+    /// Post body and comments the ideas were generated from (only with `--include-source`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceDiscussion>,
+    /// The prompt whose reply the ideas were parsed from (only with `--include-prompt`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SourceDiscussion {
+    pub body: String,
+    pub comments: Vec<Comment>,
 }
 
 /// Parse a JSON array of ideas from Gemini's response text.
 /// Gemini may wrap JSON in markdown fences like ```json ... ```
 pub fn parse_ideas(raw: &str) -> Vec<Idea> {
+    let Some(json_str) = json_array(raw) else {
+        return Vec::new();
+    };
+
+    let mut ideas = serde_json::from_str::<Vec<Idea>>(json_str).unwrap_or_default();
+    for idea in &mut ideas {
+        idea.category = idea.category.trim().to_lowercase();
+    }
+    assign_ids(&mut ideas);
+    ideas
+}
+
+/// One entry of the `--score-ideas` reply; `idea` is the 1-based number the idea was sent under.
+#[derive(Deserialize)]
+struct ScoreEntry {
+    idea: usize,
+    novelty: f64,
+    feasibility: f64,
+    market_fit: f64,
+}
+
+/// Attach the ratings in Gemini's scoring reply to `ideas`, by the number each was sent under.
+/// Ratings are rounded and clamped to 1-10. Returns how many ideas got scores.
+pub fn parse_scores(raw: &str, ideas: &mut [Idea]) -> usize {
+    let Some(entries) = json_array(raw).and_then(|json| serde_json::from_str::<Vec<ScoreEntry>>(json).ok()) else {
+        return 0;
+    };
+
+    let rating = |value: f64| value.round().clamp(1.0, 10.0) as u8;
+    let mut scored = 0;
+    for entry in entries {
+        let Some(idea) = entry.idea.checked_sub(1).and_then(|i| ideas.get_mut(i)) else {
+            continue;
+        };
+        if idea.scores.is_none() {
+            scored += 1;
+        }
+        idea.scores = Some(IdeaScores {
+            novelty: rating(entry.novelty),
+            feasibility: rating(entry.feasibility),
+            market_fit: rating(entry.market_fit),
+        });
+    }
+    scored
+}
+
+/// The JSON array in a model reply, without markdown fences or surrounding prose.
+fn json_array(raw: &str) -> Option<&str> {
     let trimmed = raw.trim();
 
     // Strip markdown code fences if present (```json ... ```)
@@ -48,37 +208,141 @@ pub fn parse_ideas(raw: &str) -> Vec<Idea> {
 
     // Find the JSON array boundaries: first '[' to last ']'
     // This handles Gemini adding extra text before or after the array
-    let json_str = match (stripped.find('['), stripped.rfind(']')) {
-        (Some(start), Some(end)) if start < end => &stripped[start..=end],
-        _ => return Vec::new(),
-    };
+    match (stripped.find('['), stripped.rfind(']')) {
+        (Some(start), Some(end)) if start < end => Some(&stripped[start..=end]),
+        _ => None,
+    }
+}
+
+/// Stable identifier for an idea: 16 hex digits of a 64-bit FNV-1a hash over the
+/// normalized product name and core problem. Identical content gets the same id in every run.
+pub fn idea_id(product_name: &str, core_problem: &str) -> String {
+    let key = format!(
+        "{}\0{}",
+        analysis::normalize(product_name),
+        analysis::normalize(core_problem)
+    );
+    stable_hash(&key)
+}
 
-    serde_json::from_str::<Vec<Idea>>(json_str).unwrap_or_default()
+/// 64-bit FNV-1a hash as 16 hex digits. Unlike `DefaultHasher`, the same input hashes
+/// the same way in every build.
+pub fn stable_hash(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
 }
 
-/// Format parsed ideas back into readable text for CLI output.
+/// Set every idea's `id` from its content, replacing whatever was there.
+pub fn assign_ids(ideas: &mut [Idea]) {
+    for idea in ideas {
+        idea.id = idea_id(&idea.product_name, &idea.core_problem);
+    }
+}
+
+/// Split ideas into those with every required field filled and a count of rejected ones.
+/// An idea is rejected if any text field is blank or it has no non-blank MVP feature.
+pub fn validate_ideas(ideas: Vec<Idea>) -> (Vec<Idea>, usize) {
+    let total = ideas.len();
+    let valid: Vec<Idea> = ideas.into_iter().filter(is_valid_idea).collect();
+    let rejected = total - valid.len();
+    (valid, rejected)
+}
+
+fn is_valid_idea(idea: &Idea) -> bool {
+    let required = [
+        &idea.product_name,
+        &idea.target_user,
+        &idea.core_problem,
+        &idea.monetization,
+        &idea.feasibility,
+    ];
+    required.iter().all(|field| !field.trim().is_empty())
+        && idea.mvp_features.iter().any(|f| !f.trim().is_empty())
+}
+
+/// Format parsed ideas back into readable text for CLI output. Fields are left as the
+/// model wrote them; markdown output re-renders with [`format_ideas_text_truncated`].
 pub fn format_ideas_text(ideas: &[Idea]) -> String {
+    format_ideas_text_truncated(ideas, None, false)
+}
+
+/// [`format_ideas_text`] with each field cut to at most `max_chars` characters (`--truncate-fields`),
+/// and with markdown syntax in the fields escaped when `markdown` is set.
+pub fn format_ideas_text_truncated(ideas: &[Idea], max_chars: Option<usize>, markdown: bool) -> String {
     if ideas.is_empty() {
         return String::from("(No structured ideas parsed)");
     }
 
+    let field = |text: &str| {
+        let text = truncate_field(text, max_chars);
+        if markdown { escape_markdown(&text) } else { text }
+    };
     let mut out = String::new();
     for (i, idea) in ideas.iter().enumerate() {
         if i > 0 {
             out.push_str("\n---\n\n");
         }
-        out.push_str(&format!("### {}. {}\n\n", i + 1, idea.product_name));
-        out.push_str(&format!("**Target User:** {}\n\n", idea.target_user));
-        out.push_str(&format!("**Core Problem:** {}\n\n", idea.core_problem));
+        out.push_str(&format!("### {}. {}\n\n", i + 1, field(&idea.product_name)));
+        out.push_str(&format!("**Target User:** {}\n\n", field(&idea.target_user)));
+        out.push_str(&format!("**Core Problem:** {}\n\n", field(&idea.core_problem)));
         out.push_str("**MVP Features:**\n");
         for feat in &idea.mvp_features {
-            out.push_str(&format!("  - {}\n", feat));
+            out.push_str(&format!("  - {}\n", field(feat)));
         }
-        out.push_str(&format!("\n**Monetization:** {}\n\n", idea.monetization));
+        out.push_str(&format!("\n**Monetization:** {}\n\n", field(&idea.monetization)));
         out.push_str(&format!(
             "**Why Feasible for Solo Builder:** {}\n",
-            idea.feasibility
+            field(&idea.feasibility)
         ));
+        if !idea.effort_estimate.trim().is_empty() {
+            out.push_str(&format!(
+                "\n**Effort Estimate:** {}\n",
+                field(&idea.effort_estimate)
+            ));
+        }
+        if !idea.category.is_empty() {
+            out.push_str(&format!("\n**Category:** {}\n", field(&idea.category)));
+        }
+        if let Some(scores) = &idea.scores {
+            out.push_str(&format!(
+                "\n**Scores:** novelty {}/10, feasibility {}/10, market fit {}/10\n",
+                scores.novelty, scores.feasibility, scores.market_fit
+            ));
+        }
+        if !idea.source_evidence.trim().is_empty() {
+            out.push_str(&format!(
+                "\n**Source Evidence:** {}\n",
+                field(&idea.source_evidence)
+            ));
+        }
+    }
+    out
+}
+
+/// Cut `text` to `max_chars` characters with an ellipsis; unchanged when `max_chars` is `None`.
+fn truncate_field(text: &str, max_chars: Option<usize>) -> String {
+    let text = text.trim();
+    match max_chars.and_then(|max| text.char_indices().nth(max)) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// Backslash-escape characters that would otherwise be read as markdown syntax,
+/// and flatten newlines so model text can't start new blocks.
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.trim().chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '|' | '<' | '>' | '~' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(c),
+        }
     }
     out
 }
@@ -88,12 +352,11 @@ pub fn extract_subreddit(url: &str) -> String {
     // URL format: https://www.reddit.com/r/SubredditName/comments/...
     let parts: Vec<&str> = url.split('/').collect();
     for (i, segment) in parts.iter().enumerate() {
-        if *segment == "r" {
-            if let Some(name) = parts.get(i + 1) {
-                if !name.is_empty() {
-                    return name.to_string();
-                }
-            }
+        if *segment == "r"
+            && let Some(name) = parts.get(i + 1)
+            && !name.is_empty()
+        {
+            return name.to_string();
         }
     }
     "unknown".to_string()
@@ -1,41 +1,522 @@
-use crate::cli::OutputFormat;
-use crate::models::AnalysisResult;
+use crate::analysis;
+use crate::cli::{OutputFormat, ResultOrder};
+use crate::errors::AppError;
+use crate::export::sheets::{Column, DEFAULT_COLUMNS};
+use crate::models::{assign_ids, escape_markdown, format_ideas_text_truncated, AnalysisResult};
+use crate::services::gemini::Usage;
 
-pub fn format_results(results: &[AnalysisResult], format: &OutputFormat) -> String {
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Top-level shape of `--format json` output.
+#[derive(Serialize, Deserialize)]
+pub struct JsonRun {
+    pub generated_at: String,
+    pub tool_version: String,
+    pub results: Vec<AnalysisResult>,
+}
+
+/// How a saved run was produced, written next to it as `<name>.meta.json`.
+#[derive(Serialize, Deserialize)]
+pub struct RunMeta {
+    pub generated_at: String,
+    pub tool_version: String,
+    /// The command line, with `--gemini-key` values redacted.
+    pub command: Vec<String>,
+    pub models: Vec<String>,
+    pub posts: usize,
+    pub ideas: usize,
+    pub total_tokens: u64,
+    pub elapsed_secs: f64,
+}
+
+/// Describe a finished run for its metadata sidecar.
+pub fn format_meta(results: &[AnalysisResult], usage: Usage, elapsed: std::time::Duration) -> String {
+    let meta = RunMeta {
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        command: command_line(),
+        models: usage.models,
+        posts: results.len(),
+        ideas: results.iter().map(|r| r.ideas.len()).sum(),
+        total_tokens: usage.total_tokens,
+        elapsed_secs: (elapsed.as_secs_f64() * 10.0).round() / 10.0,
+    };
+    serde_json::to_string_pretty(&meta).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// `--save-append`: add this run's metadata to an existing sidecar, which becomes a JSON array
+/// with one entry per run, oldest first. A sidecar from a plain `--save` (a single object)
+/// becomes the first entry; one that can't be read is replaced.
+pub fn append_meta(existing: &str, meta: &str) -> String {
+    let Ok(meta) = serde_json::from_str::<RunMeta>(meta) else {
+        return meta.to_string();
+    };
+    let mut runs = serde_json::from_str::<Vec<RunMeta>>(existing)
+        .or_else(|_| serde_json::from_str::<RunMeta>(existing).map(|run| vec![run]))
+        .unwrap_or_default();
+    runs.push(meta);
+    serde_json::to_string_pretty(&runs).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Top-level shape of the `--dedup-report` file.
+#[derive(Serialize)]
+struct DedupReport<'a> {
+    generated_at: String,
+    merged: usize,
+    merges: &'a [analysis::Merge],
+}
+
+/// List the run's duplicate merges, in the order they happened.
+pub fn format_dedup_report(merges: &[analysis::Merge]) -> String {
+    let report = DedupReport {
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        merged: merges.len(),
+        merges,
+    };
+    serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// This process's arguments, with the `--gemini-key` value replaced so it is safe to record.
+pub fn command_line() -> Vec<String> {
+    let mut command = Vec::new();
+    let mut redact_next = false;
+    for arg in std::env::args() {
+        if redact_next {
+            command.push("<redacted>".to_string());
+            redact_next = false;
+        } else if arg.starts_with("--gemini-key=") {
+            command.push("--gemini-key=<redacted>".to_string());
+        } else {
+            redact_next = arg == "--gemini-key";
+            command.push(arg);
+        }
+    }
+    command
+}
+
+/// Saved runs from before the envelope was added are a bare results array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedJson {
+    Envelope(JsonRun),
+    Bare(Vec<AnalysisResult>),
+}
+
+/// Load results previously saved with `--format json`, with or without the envelope.
+pub fn read_json_run(path: &str) -> Result<Vec<AnalysisResult>, AppError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", path, e)))?;
+    let saved: SavedJson = serde_json::from_str(&content).map_err(|e| {
+        AppError::InvalidInput(format!("{} is not a saved JSON run: {}", path, e))
+    })?;
+    let mut results = match saved {
+        SavedJson::Envelope(run) => run.results,
+        SavedJson::Bare(results) => results,
+    };
+    // Runs saved before ideas had ids get them computed on load
+    for r in &mut results {
+        assign_ids(&mut r.ideas);
+    }
+    Ok(results)
+}
+
+/// Expand `*` and `?` in the file-name part of each pattern into the matching files, sorted by
+/// name. Paths without wildcards are kept as given. A pattern that matches nothing is an error.
+/// Wildcards never match `.meta.json` sidecars, which `--save` writes next to every run.
+pub fn expand_paths(patterns: &[String]) -> Result<Vec<String>, AppError> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?']) {
+            paths.push(pattern.clone());
+            continue;
+        }
+        let path = std::path::Path::new(pattern);
+        // A bare pattern like `week-*.json` lists the working directory and yields bare names
+        let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let dir = parent.unwrap_or(std::path::Path::new("."));
+        let name_pattern = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if dir.to_string_lossy().contains(['*', '?']) {
+            return Err(AppError::InvalidInput(format!(
+                "{}: wildcards are only supported in the file name",
+                pattern
+            )));
+        }
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| AppError::Io(format!("Failed to read {}: {}", dir.display(), e)))?;
+        let mut matched: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                !name.ends_with(".meta.json") && wildcard_match(name_pattern, &name)
+            })
+            .map(|entry| match parent {
+                Some(dir) => dir.join(entry.file_name()).to_string_lossy().into_owned(),
+                None => entry.file_name().to_string_lossy().into_owned(),
+            })
+            .collect();
+        if matched.is_empty() {
+            return Err(AppError::InvalidInput(format!("No files match {}", pattern)));
+        }
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Whether `name` matches `pattern`, where `*` is any run of characters and `?` is one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried against, for backtracking
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((after_star, tried)) = star {
+            p = after_star;
+            n = tried + 1;
+            star = Some((after_star, tried + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Expand `{date}`, `{subreddit}`, and `{format}` placeholders in a `--save` path.
+pub fn render_save_path(template: &str, format: &OutputFormat, subreddit: &str) -> String {
+    template
+        .replace("{date}", &Utc::now().format("%Y-%m-%d").to_string())
+        .replace("{subreddit}", subreddit)
+        .replace("{format}", format.extension())
+}
+
+/// Where one format's output is saved. Under `--format all`, each file gets its format's
+/// extension in place of the template's, unless the template already places it with `{format}`.
+pub fn save_path(template: &str, format: &OutputFormat, subreddit: &str, all_formats: bool) -> String {
+    let path = render_save_path(template, format, subreddit);
+    if !all_formats || template.contains("{format}") {
+        return path;
+    }
+    std::path::Path::new(&path)
+        .with_extension(format.extension())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The metadata sidecar for a saved file: its extension replaced by `.meta.json`.
+pub fn meta_path(path: &str) -> String {
+    std::path::Path::new(path)
+        .with_extension("meta.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Group results by subreddit, keeping subreddits in first-seen order.
+pub fn group_by_subreddit(results: &[AnalysisResult]) -> Vec<(&str, Vec<&AnalysisResult>)> {
+    let mut groups: Vec<(&str, Vec<&AnalysisResult>)> = Vec::new();
+    for r in results {
+        match groups.iter_mut().find(|(sub, _)| *sub == r.subreddit) {
+            Some((_, group)) => group.push(r),
+            None => groups.push((&r.subreddit, vec![r])),
+        }
+    }
+    groups
+}
+
+/// Spreadsheet-compatibility settings for CSV output.
+#[derive(Clone, Copy)]
+pub struct CsvOptions {
+    /// Field separator: `,` normally, `;` for locales where Excel expects it.
+    pub delimiter: char,
+    /// Start saved CSV files with a UTF-8 byte order mark so Excel detects the encoding.
+    pub bom: bool,
+}
+
+/// Results ordered most engaging first. Stable, so ties keep their fetched order.
+pub fn sort_results(results: &[AnalysisResult], order: ResultOrder) -> Vec<AnalysisResult> {
+    let mut sorted = results.to_vec();
+    match order {
+        ResultOrder::Score => sorted.sort_by_key(|r| std::cmp::Reverse(r.score)),
+        ResultOrder::Comments => sorted.sort_by_key(|r| std::cmp::Reverse(r.num_comments)),
+        ResultOrder::Ideas => sorted.sort_by_key(|r| std::cmp::Reverse(r.ideas.len())),
+    }
+    sorted
+}
+
+/// Render results in `format`. `truncate` cuts idea fields in text and markdown output only.
+pub fn format_results(
+    results: &[AnalysisResult],
+    format: &OutputFormat,
+    csv: CsvOptions,
+    truncate: Option<usize>,
+) -> String {
     match format {
-        OutputFormat::Text => format_text(results),
+        OutputFormat::Text | OutputFormat::All => format_text(results, truncate),
         OutputFormat::Json => format_json(results),
-        OutputFormat::Markdown => format_markdown(results),
+        OutputFormat::Markdown => format_markdown(results, truncate),
+        OutputFormat::MarkdownTable => format_markdown_table(results),
+        OutputFormat::Csv => format_csv(results, csv.delimiter),
     }
 }
 
-fn format_text(results: &[AnalysisResult]) -> String {
+/// A result's ideas as text, re-rendered with fields cut under `--truncate-fields` and, for
+/// markdown, escaped. Raw replies that never parsed into ideas are shown as they are.
+fn ideas_text(result: &AnalysisResult, truncate: Option<usize>, markdown: bool) -> String {
+    if result.ideas.is_empty() || (truncate.is_none() && !markdown) {
+        return result.ideas_text.clone();
+    }
+    format_ideas_text_truncated(&result.ideas, truncate, markdown)
+}
+
+fn format_text(results: &[AnalysisResult], truncate: Option<usize>) -> String {
+    let groups = group_by_subreddit(results);
+    let show_headers = groups.len() > 1;
+    // Posts inside a subreddit section use a lighter rule than the subreddit banner
+    let separator = if show_headers {
+        "\n────────────────────────────────────────\n\n"
+    } else {
+        "\n════════════════════════════════════════\n\n"
+    };
+
     let mut out = String::new();
-    for (i, r) in results.iter().enumerate() {
-        if i > 0 {
-            out.push_str("\n════════════════════════════════════════\n\n");
+    let mut i = 0;
+    for (subreddit, group) in &groups {
+        if show_headers {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("════════════ r/{} ════════════\n\n", subreddit));
+        }
+        for (j, r) in group.iter().enumerate() {
+            if j > 0 || (i > 0 && !show_headers) {
+                out.push_str(separator);
+            }
+            i += 1;
+            out.push_str(&format!("URL: {}\n", r.url));
+            out.push_str(&format!("Title: {}\n\n", r.title));
+            out.push_str(&format!("Ideas:\n{}\n", ideas_text(r, truncate, false)));
         }
-        out.push_str(&format!("URL: {}\n", r.url));
-        out.push_str(&format!("Title: {}\n\n", r.title));
-        out.push_str(&format!("Ideas:\n{}\n", r.ideas_text));
     }
     out
 }
 
 fn format_json(results: &[AnalysisResult]) -> String {
-    serde_json::to_string_pretty(results).unwrap_or_else(|_| "[]".to_string())
+    let run = JsonRun {
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        results: results.to_vec(),
+    };
+    serde_json::to_string_pretty(&run).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn format_markdown(results: &[AnalysisResult], truncate: Option<usize>) -> String {
+    let groups = group_by_subreddit(results);
+    // With several subreddits, posts nest one heading level below a subreddit heading
+    let show_headers = groups.len() > 1;
+    let post_heading = if show_headers { "###" } else { "##" };
+    let ideas_heading = if show_headers { "####" } else { "###" };
+
+    let mut out = String::from("# Reddit Startup Analysis\n\n");
+    let mut i = 0;
+    for (subreddit, group) in &groups {
+        if show_headers {
+            if i > 0 {
+                out.push_str("---\n\n");
+            }
+            out.push_str(&format!("## r/{}\n\n", subreddit));
+        }
+        for (j, r) in group.iter().enumerate() {
+            if j > 0 || (i > 0 && !show_headers) {
+                out.push_str("---\n\n");
+            }
+            i += 1;
+            out.push_str(&format!("{} Post {}\n\n", post_heading, i));
+            out.push_str(&format!("**URL:** {}\n\n", r.url));
+            out.push_str(&format!("**Title:** {}\n\n", escape_markdown(&r.title)));
+            out.push_str(&format!("{} Ideas\n\n{}\n\n", ideas_heading, ideas_text(r, truncate, true)));
+        }
+    }
+
+    let ideas: Vec<_> = results.iter().flat_map(|r| r.ideas.iter().cloned()).collect();
+    let keywords = analysis::top_keywords(&ideas, 15);
+    if !keywords.is_empty() {
+        out.push_str("---\n\n## Top Keywords\n\n");
+        for (word, count) in &keywords {
+            out.push_str(&format!("- **{}** ({})\n", word, count));
+        }
+        out.push('\n');
+    }
+    out
 }
 
-fn format_markdown(results: &[AnalysisResult]) -> String {
+/// Longest cell in the markdown table before it is cut with an ellipsis.
+const TABLE_CELL_MAX: usize = 80;
+
+fn format_markdown_table(results: &[AnalysisResult]) -> String {
     let mut out = String::from("# Reddit Startup Analysis\n\n");
-    for (i, r) in results.iter().enumerate() {
-        if i > 0 {
-            out.push_str("---\n\n");
+    out.push_str("| # | Product | Target User | Problem | Monetization | Effort | Category | Source |\n");
+    out.push_str("|---|---------|-------------|---------|--------------|--------|----------|--------|\n");
+
+    let mut n = 0;
+    for r in results {
+        for idea in &r.ideas {
+            n += 1;
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} | [r/{}]({}) |\n",
+                n,
+                table_cell(&idea.product_name),
+                table_cell(&idea.target_user),
+                table_cell(&idea.core_problem),
+                table_cell(&idea.monetization),
+                table_cell(&idea.effort_estimate),
+                table_cell(&idea.category),
+                r.subreddit,
+                r.url
+            ));
         }
-        out.push_str(&format!("## Post {}\n\n", i + 1));
-        out.push_str(&format!("**URL:** {}\n\n", r.url));
-        out.push_str(&format!("**Title:** {}\n\n", r.title));
-        out.push_str(&format!("### Ideas\n\n{}\n\n", r.ideas_text));
+    }
+
+    if n == 0 {
+        out.push_str("\n_No structured ideas parsed._\n");
     }
     out
 }
+
+/// Make text safe for a single table cell: one line, markdown-escaped, truncated.
+fn table_cell(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cut = match flat.char_indices().nth(TABLE_CELL_MAX) {
+        Some((idx, _)) => format!("{}…", flat[..idx].trim_end()),
+        None => flat,
+    };
+    escape_markdown(&cut)
+}
+
+/// One row per idea, in the Sheets export's default column order (minus the date).
+fn format_csv(results: &[AnalysisResult], delimiter: char) -> String {
+    let columns: Vec<Column> = DEFAULT_COLUMNS.iter().copied().filter(|c| *c != Column::Date).collect();
+    let header: Vec<&str> = columns.iter().map(|c| c.header()).collect();
+    let mut out = header.join(&delimiter.to_string());
+    out.push('\n');
+    for r in results {
+        for idea in &r.ideas {
+            let fields: Vec<String> = columns.iter().map(|c| c.cell(r, idea, "")).collect();
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f, delimiter)).collect();
+            out.push_str(&row.join(&delimiter.to_string()));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a delimiter, quote, or newline (RFC 4180).
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{format_ideas_text, Idea};
+
+    #[test]
+    fn save_path_under_format_all() {
+        let all = |template: &str, format: OutputFormat| save_path(template, &format, "", true);
+        // Without {format}, the format's extension replaces the template's
+        assert_eq!(all("out", OutputFormat::Json), "out.json");
+        assert_eq!(all("out.txt", OutputFormat::Csv), "out.csv");
+        // With {format}, the template is used as written
+        assert_eq!(all("out.{format}", OutputFormat::Json), "out.json");
+        assert_eq!(all("out-{format}", OutputFormat::Json), "out-json");
+        assert_eq!(all("{format}/ideas.{format}", OutputFormat::Markdown), "md/ideas.md");
+        // A single format keeps the template's own extension
+        assert_eq!(save_path("out.txt", &OutputFormat::Json, "", false), "out.txt");
+    }
+
+    #[test]
+    fn append_meta_keeps_every_run() {
+        let run = |posts: usize| {
+            let usage = Usage { models: vec!["gemini-2.5-flash".into()], total_tokens: 10 };
+            let meta = format_meta(&[], usage, std::time::Duration::ZERO);
+            meta.replace("\"posts\": 0", &format!("\"posts\": {}", posts))
+        };
+        let posts = |text: &str| -> Vec<usize> {
+            serde_json::from_str::<Vec<RunMeta>>(text).unwrap().iter().map(|m| m.posts).collect()
+        };
+
+        assert_eq!(posts(&append_meta("", &run(1))), [1]);
+        // A sidecar from a plain --save becomes the first entry
+        let appended = append_meta(&run(1), &run(2));
+        let appended = append_meta(&appended, &run(3));
+        assert_eq!(posts(&appended), [1, 2, 3]);
+        assert!(appended.find("generated_at").unwrap() < appended.find("elapsed_secs").unwrap());
+    }
+
+    fn result_with_markup() -> AnalysisResult {
+        let ideas = vec![Idea {
+            id: String::new(),
+            product_name: "`inv` *CLI*".into(),
+            target_user: "Freelancers | agencies".into(),
+            core_problem: "Invoices_v2 get lost".into(),
+            mvp_features: vec!["Parse **PDF** totals".into()],
+            monetization: "$5/month".into(),
+            feasibility: "# Small scope".into(),
+            source_evidence: String::new(),
+            effort_estimate: String::new(),
+            category: String::new(),
+            scores: None,
+        }];
+        AnalysisResult {
+            subreddit: "freelance".into(),
+            url: "https://www.reddit.com/r/freelance/comments/abc/x".into(),
+            title: "Tracking *unpaid* invoices".into(),
+            ideas_text: format_ideas_text(&ideas),
+            ideas,
+            score: 0,
+            num_comments: 0,
+            source: None,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn ideas_text_keeps_markup_characters_unescaped() {
+        let result = result_with_markup();
+        assert!(result.ideas_text.contains("`inv` *CLI*"));
+        assert!(result.ideas_text.contains("Freelancers | agencies"));
+        assert!(!result.ideas_text.contains('\\'));
+
+        let text = format_text(std::slice::from_ref(&result), None);
+        assert!(text.contains("Parse **PDF** totals"));
+        assert!(!text.contains('\\'));
+
+        let json = format_json(std::slice::from_ref(&result));
+        assert!(json.contains("`inv` *CLI*"));
+    }
+
+    #[test]
+    fn markdown_escapes_backticks_asterisks_and_pipes() {
+        let result = result_with_markup();
+        let markdown = format_markdown(std::slice::from_ref(&result), None);
+        assert!(markdown.contains(r"\`inv\` \*CLI\*"));
+        assert!(markdown.contains(r"Freelancers \| agencies"));
+        assert!(markdown.contains(r"Parse \*\*PDF\*\* totals"));
+        assert!(markdown.contains(r"Invoices\_v2"));
+
+        let table = format_markdown_table(std::slice::from_ref(&result));
+        assert!(table.contains(r"\`inv\` \*CLI\*"));
+        assert!(table.contains(r"Freelancers \| agencies"));
+    }
+}
@@ -1,44 +1,437 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use futures::future::BoxFuture;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::analysis;
+use crate::cli::CommentStrategy;
+use crate::config::CommentWeighting;
 use crate::errors::AppError;
-use crate::models::RedditPost;
+use crate::models::{Comment, Idea, RedditPost};
+use crate::utils::throttle;
 
-const MODELS: &[&str] = &[
+/// Fallback chain, tried in order on each call.
+pub const MODELS: &[&str] = &[
     "gemini-2.5-flash",
     "gemini-flash-latest",
     "gemini-2.5-flash-lite",
     "gemini-2.0-flash",
 ];
 
+/// Stronger model `--escalate-on-parse-fail` retries on unless `--escalation-model` names another.
+pub const ESCALATION_MODEL: &str = "gemini-2.5-pro";
+
+/// Role framing sent as the `systemInstruction` unless `--persona` replaces it.
+pub const DEFAULT_PERSONA: &str =
+    "You are a pragmatic product strategist focused on small, buildable digital products.";
+
+/// Models that answered and tokens billed so far this run, for the `--save` metadata file.
+static USAGE: Mutex<Usage> = Mutex::new(Usage {
+    models: Vec::new(),
+    total_tokens: 0,
+});
+
+/// Gemini usage accumulated across a run.
+#[derive(Clone, Default, serde::Serialize)]
+pub struct Usage {
+    /// Models that returned a reply, in first-used order.
+    pub models: Vec<String>,
+    /// Sum of `usageMetadata.totalTokenCount` over every reply.
+    pub total_tokens: u64,
+}
+
+/// Snapshot of the usage recorded so far.
+pub fn usage() -> Usage {
+    USAGE.lock().map(|u| u.clone()).unwrap_or_default()
+}
+
+fn record_usage(model: &str, data: &serde_json::Value) {
+    if let Ok(mut usage) = USAGE.lock() {
+        if !usage.models.iter().any(|m| m == model) {
+            usage.models.push(model.to_string());
+        }
+        usage.total_tokens += data["usageMetadata"]["totalTokenCount"].as_u64().unwrap_or(0);
+    }
+}
+
+/// Harm categories covered when a custom safety threshold is configured.
+const SAFETY_CATEGORIES: &[&str] = &[
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+/// Largest image attached under `--vision`; bigger downloads fall back to text-only.
+const MAX_IMAGE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Image types Gemini accepts as inline data.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/heic", "image/heif"];
+
+/// Number of highest-weighted comments flagged as "Most upvoted" in the prompt.
+const HIGHLIGHTED_COMMENTS: usize = 3;
+
+/// Settings that shape how a post is turned into a prompt and sent to Gemini.
+#[derive(Clone, Default)]
+pub struct PromptOptions {
+    pub weighting: CommentWeighting,
+    /// Skip the post body and base ideas on the comments alone.
+    pub comments_only: bool,
+    /// The post is several threads combined; ask for ideas that span them.
+    pub aggregate: bool,
+    /// Subreddit name and description, shown before the discussion (`--with-context`).
+    pub community: Option<(String, String)>,
+    /// Re-prompt once in strict JSON mode if the first reply has no parseable ideas.
+    pub retry_empty: bool,
+    /// Prefix each comment with its id so the model can cite its sources.
+    pub comment_refs: bool,
+    /// How comments are written into the prompt.
+    pub comment_strategy: CommentStrategy,
+    /// Add a stronger JSON-only instruction and request a JSON response MIME type.
+    pub strict_json: bool,
+    /// User-supplied requirements added after the field list (`--guidance`).
+    pub guidance: Vec<String>,
+    /// Categories the model picks each idea's `category` from.
+    pub categories: Vec<String>,
+    /// Role framing sent as the `systemInstruction`, apart from the task. Empty sends none.
+    pub persona: String,
+    /// Ask for a specific number of additional ideas instead of the usual set.
+    pub top_up: Option<TopUp>,
+    /// Per-call timeout; `None` falls back to the HTTP client's default.
+    pub timeout: Option<Duration>,
+    /// Language name for idea text (e.g. "Spanish"); `None` or "English" adds no instruction.
+    pub language: Option<&'static str>,
+    /// Attach the post's image (if any) to the request as inline data.
+    pub vision: bool,
+    /// Gemini `safetySettings` threshold applied to every harm category; `None` keeps the API default.
+    pub safety_threshold: Option<String>,
+    /// Log each model attempt and success, not just fallbacks and errors.
+    pub verbose: bool,
+    /// Show a spinner on stderr while waiting for a model (hidden when stderr is not a TTY).
+    pub show_progress: bool,
+    /// Ask only this model instead of walking `MODELS` (`--escalate-on-parse-fail`).
+    pub model: Option<String>,
+}
+
+/// A follow-up request for more ideas when the first reply came up short.
+#[derive(Clone, Debug)]
+pub struct TopUp {
+    pub count: usize,
+    /// Product names already generated, which the model must not repeat.
+    pub existing: Vec<String>,
+}
+
+/// The model's raw reply, with the prompt that produced it.
+pub struct Reply {
+    pub text: String,
+    pub prompt: String,
+    /// The model that answered; `None` for `--mock` fixtures.
+    pub model: Option<String>,
+}
+
+/// Turns a post into the model's raw reply. Implemented by [`GeminiClient`] and by
+/// `--mock` fixtures.
+pub trait IdeaGenerator: Send + Sync {
+    fn generate<'a>(
+        &'a self,
+        post: &'a RedditPost,
+        options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<Reply, AppError>>;
+
+    /// Rate ideas already generated for `post` (`--score-ideas`), returning the raw reply
+    /// for [`crate::models::parse_scores`].
+    fn score<'a>(
+        &'a self,
+        post: &'a RedditPost,
+        ideas: &'a [Idea],
+        options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<String, AppError>>;
+}
+
+/// The Gemini API, with model fallback.
+pub struct GeminiClient<'c> {
+    pub client: &'c reqwest::Client,
+    pub api_key: &'c str,
+}
+
+impl IdeaGenerator for GeminiClient<'_> {
+    fn generate<'a>(
+        &'a self,
+        post: &'a RedditPost,
+        options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<Reply, AppError>> {
+        Box::pin(generate_ideas(self.client, self.api_key, post, options))
+    }
+
+    fn score<'a>(
+        &'a self,
+        post: &'a RedditPost,
+        ideas: &'a [Idea],
+        options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(score_ideas(self.client, self.api_key, post, ideas, options))
+    }
+}
+
 pub async fn generate_ideas(
     client: &reqwest::Client,
     api_key: &str,
     post: &RedditPost,
+    options: &PromptOptions,
+) -> Result<Reply, AppError> {
+    if options.comments_only && post.comments.is_empty() {
+        return Err(AppError::InvalidInput(format!(
+            "--comments-only requires comments, but {} has no usable comments",
+            post.url
+        )));
+    }
+
+    let summary = if options.comment_strategy == CommentStrategy::Summarized && !post.comments.is_empty() {
+        match summarize_comments(client, api_key, post, options).await {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                eprintln!("⚠️  Comment summary failed, sending comments in full: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let image = match (&post.image_url, options.vision) {
+        (Some(url), true) => match download_image(client, url, options.timeout).await {
+            Ok(image) => Some(image),
+            Err(e) => {
+                eprintln!("⚠️  Could not attach image {} (continuing text-only): {}", url, e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let mut prompt = build_prompt(post, options, summary.as_deref());
+    if image.is_some() {
+        prompt.push_str("\nThe post's image is attached. Treat what it shows as part of the discussion.\n");
+    }
+    let system = Some(options.persona.as_str()).filter(|p| !p.trim().is_empty());
+    let payload = build_payload(&prompt, options, options.strict_json, image.as_ref(), system);
+    let (text, model) = match send_to_models(client, api_key, &payload, options, &post.url).await {
+        // Some models reject systemInstruction outright; send the persona as part of the prompt
+        Err(AppError::ExternalService(msg))
+            if system.is_some() && msg.to_lowercase().contains("instruction") =>
+        {
+            eprintln!("⚠️  Gemini rejected the system instruction, resending with the persona in the prompt...");
+            let inline = format!("{}\n\n{}", options.persona.trim(), prompt);
+            let payload = build_payload(&inline, options, options.strict_json, image.as_ref(), None);
+            send_to_models(client, api_key, &payload, options, &post.url).await?
+        }
+        other => other?,
+    };
+    Ok(Reply {
+        text,
+        prompt,
+        model: Some(model),
+    })
+}
+
+/// Ask for a 1-10 novelty, feasibility, and market-fit rating of each idea (`--score-ideas`).
+async fn score_ideas(
+    client: &reqwest::Client,
+    api_key: &str,
+    post: &RedditPost,
+    ideas: &[Idea],
+    options: &PromptOptions,
+) -> Result<String, AppError> {
+    let prompt = build_score_prompt(post, ideas);
+    let payload = build_payload(&prompt, options, true, None, None);
+    send_to_models(client, api_key, &payload, options, &post.url)
+        .await
+        .map(|(text, _)| text)
+}
+
+/// The `--score-ideas` prompt: the post's title for context, then each idea by number.
+pub fn build_score_prompt(post: &RedditPost, ideas: &[Idea]) -> String {
+    let mut prompt = String::from(
+        "You are reviewing product ideas drawn from a Reddit discussion. Rate each idea from 1 \
+         (poor) to 10 (excellent) on three dimensions:\n\
+         - novelty: how different it is from products that already exist\n\
+         - feasibility: how realistic it is for one developer to build and run\n\
+         - market_fit: how clearly the target users have the problem and would pay to solve it\n\
+         Be critical and use the whole scale; do not give every idea the same ratings.\n",
+    );
+    prompt.push_str(&format!("\nDiscussion: {}\n\nIdeas:\n", post.title));
+    for (i, idea) in ideas.iter().enumerate() {
+        prompt.push_str(&format!(
+            "{}. {}: {} For: {}. Features: {}. Monetization: {}\n",
+            i + 1,
+            idea.product_name,
+            idea.core_problem,
+            idea.target_user,
+            idea.mvp_features.join("; "),
+            idea.monetization
+        ));
+    }
+    prompt.push_str(
+        "\nReply with only a JSON array holding one object per idea, in this shape:\n\
+         [{\"idea\": 1, \"novelty\": 7, \"feasibility\": 8, \"market_fit\": 6}]\n",
+    );
+    prompt
+}
+
+/// Condense a post's comments into a short list of key points (the `summarized` strategy).
+async fn summarize_comments(
+    client: &reqwest::Client,
+    api_key: &str,
+    post: &RedditPost,
+    options: &PromptOptions,
 ) -> Result<String, AppError> {
-    let prompt = build_prompt(post);
+    let mut prompt = String::from(
+        "Condense the following Reddit comments into at most 10 short bullet points. Capture distinct \
+         pain points, requests, workarounds, and recurring complaints, and keep concrete details \
+         (tools, prices, numbers). Merge points that repeat. Reply with the bullet list only.\n",
+    );
+    if options.comment_refs {
+        prompt.push_str("Keep each [c:id] reference next to the point it supports.\n");
+    }
+    prompt.push_str(&format!("\nPost title: {}\n\nComments:\n", post.title));
+    for comment in rank_comments(&post.comments, &options.weighting) {
+        prompt.push_str(&format!("- {}{}\n", comment_ref(comment, options), comment.body));
+        if let Some(reply) = &comment.top_reply {
+            prompt.push_str(&reply_line(reply, &reply.body, options));
+        }
+    }
+
+    let payload = build_payload(&prompt, options, false, None, None);
+    send_to_models(client, api_key, &payload, options, &post.url)
+        .await
+        .map(|(text, _)| text)
+}
+
+/// An image ready to send as an `inlineData` part.
+struct InlineImage {
+    mime_type: String,
+    /// Base64-encoded image bytes.
+    data: String,
+}
+
+/// Download an image for `--vision`, rejecting types Gemini can't read and oversized files.
+async fn download_image(
+    client: &reqwest::Client,
+    url: &str,
+    timeout: Option<Duration>,
+) -> Result<InlineImage, String> {
+    let _slot = throttle::request_slot().await;
+    let mut request = client.get(url);
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("download returned {}", response.status()));
+    }
+
+    let mime_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_lowercase())
+        .unwrap_or_default();
+    if !IMAGE_TYPES.contains(&mime_type.as_str()) {
+        return Err(format!("unsupported content type '{}'", mime_type));
+    }
+
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!("image is {} bytes, limit is {}", bytes.len(), MAX_IMAGE_BYTES));
+    }
 
-    let payload = serde_json::json!({
+    Ok(InlineImage {
+        mime_type,
+        data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+    })
+}
+
+/// Wrap a prompt in a request body, with safety settings and, for strict mode, a JSON MIME type.
+fn build_payload(
+    prompt: &str,
+    options: &PromptOptions,
+    json_response: bool,
+    image: Option<&InlineImage>,
+    system: Option<&str>,
+) -> serde_json::Value {
+    let mut parts = vec![serde_json::json!({ "text": prompt })];
+    if let Some(image) = image {
+        parts.push(serde_json::json!({
+            "inlineData": { "mimeType": image.mime_type, "data": image.data }
+        }));
+    }
+    let mut payload = serde_json::json!({
         "contents": [{
-            "parts": [{
-                "text": prompt
-            }]
+            "parts": parts
         }]
     });
+    if let Some(system) = system {
+        payload["systemInstruction"] = serde_json::json!({ "parts": [{ "text": system }] });
+    }
+    if let Some(threshold) = &options.safety_threshold {
+        let settings: Vec<serde_json::Value> = SAFETY_CATEGORIES
+            .iter()
+            .map(|c| serde_json::json!({ "category": c, "threshold": threshold }))
+            .collect();
+        payload["safetySettings"] = serde_json::Value::Array(settings);
+    }
+    if json_response {
+        payload["generationConfig"] = serde_json::json!({ "responseMimeType": "application/json" });
+    }
+    payload
+}
 
-    for (i, model) in MODELS.iter().enumerate() {
+/// Send `payload` to each model in turn until one answers, returning its text and the
+/// model's name. `options.model` narrows the chain to that one model.
+async fn send_to_models(
+    client: &reqwest::Client,
+    api_key: &str,
+    payload: &serde_json::Value,
+    options: &PromptOptions,
+    post_url: &str,
+) -> Result<(String, String), AppError> {
+    let chain: Vec<&str> = match &options.model {
+        Some(model) => vec![model.as_str()],
+        None => MODELS.to_vec(),
+    };
+    for (i, model) in chain.iter().enumerate() {
+        // The key goes in a header rather than the query string so it never appears in error URLs
         let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model, api_key
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            model
         );
 
-        eprintln!("Attempting API call with model: {}", model);
+        if options.verbose {
+            eprintln!("Attempting API call with model: {}", model);
+        }
 
-        let res = match client
+        let mut request = client
             .post(&url)
             .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await
-        {
+            .header("x-goog-api-key", api_key)
+            .json(payload);
+        if let Some(timeout) = options.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let _slot = throttle::request_slot().await;
+        let spinner = progress_spinner(options.show_progress, model);
+        let sent = request.send().await;
+        spinner.finish_and_clear();
+
+        let res = match sent {
             Ok(r) => r,
+            Err(e) if e.is_timeout() => {
+                eprintln!("{} timed out, trying next model...", model);
+                continue;
+            }
             Err(e) => {
                 eprintln!("Request failed for {}: {}", model, e);
                 continue;
@@ -52,7 +445,7 @@ pub async fn generate_ideas(
                 "{} is overloaded/rate-limited ({}), trying next model...",
                 model, status
             );
-            if i < MODELS.len() - 1 {
+            if i < chain.len() - 1 {
                 continue;
             }
         }
@@ -63,7 +456,7 @@ pub async fn generate_ideas(
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
             eprintln!("API error ({}): {}", status, error_text);
-            if i < MODELS.len() - 1 {
+            if i < chain.len() - 1 {
                 continue;
             }
             return Err(AppError::ExternalService(format!(
@@ -76,6 +469,13 @@ pub async fn generate_ideas(
             AppError::ExternalService(format!("Failed to parse Gemini response: {}", e))
         })?;
 
+        if let Some(reason) = blocked_reason(&data) {
+            return Err(AppError::ContentBlocked(format!(
+                "Gemini blocked the request for {}: {}",
+                post_url, reason
+            )));
+        }
+
         let text = data
             .get("candidates")
             .and_then(|c| c.get(0))
@@ -89,8 +489,11 @@ pub async fn generate_ideas(
             })?
             .to_string();
 
-        eprintln!("Successfully got response from {}", model);
-        return Ok(text);
+        record_usage(model, &data);
+        if options.verbose {
+            eprintln!("Successfully got response from {}", model);
+        }
+        return Ok((text, model.to_string()));
     }
 
     Err(AppError::ExternalService(
@@ -98,12 +501,183 @@ pub async fn generate_ideas(
     ))
 }
 
-fn build_prompt(post: &RedditPost) -> String {
-    let mut prompt = String::from(
-        "You are a pragmatic product strategist focused on small, buildable digital products.\n\n\
-         Analyze the following Reddit discussion (post + comments) and identify concrete pain points, \
-         frustrations, unmet needs, or repeated patterns.\n\n\
-         Your task is to generate 3 highly practical micro-SaaS or small product ideas that:\n\n\
+/// The model to retry on after `answered` gave an unparseable reply: `designated`, or
+/// [`ESCALATION_MODEL`]. `None` when that is no step up: the model that just failed, or one
+/// further down the fallback chain, whose later entries are all lighter models.
+pub fn escalation_model(answered: Option<&str>, designated: Option<&str>) -> Option<String> {
+    let target = designated.unwrap_or(ESCALATION_MODEL);
+    if answered == Some(target) {
+        return None;
+    }
+    let rank = |model: &str| MODELS.iter().position(|m| *m == model);
+    if let (Some(answered), Some(target)) = (answered.and_then(rank), rank(target))
+        && target > answered
+    {
+        return None;
+    }
+    Some(target.to_string())
+}
+
+/// A model as reported by the `models` endpoint.
+pub struct ModelInfo {
+    /// Short name without the `models/` prefix, as used in request URLs.
+    pub name: String,
+    pub methods: Vec<String>,
+}
+
+impl ModelInfo {
+    pub fn supports_generate(&self) -> bool {
+        self.methods.iter().any(|m| m == "generateContent")
+    }
+}
+
+/// List every model the API key can see, following pagination.
+pub async fn list_models(client: &reqwest::Client, api_key: &str) -> Result<Vec<ModelInfo>, AppError> {
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let _slot = throttle::request_slot().await;
+        let mut request = client
+            .get("https://generativelanguage.googleapis.com/v1beta/models")
+            .header("x-goog-api-key", api_key)
+            .query(&[("pageSize", "1000")]);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
+
+        let res = request
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to list Gemini models: {}", e)))?;
+        let status = res.status();
+        if !status.is_success() {
+            let error_text = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::ExternalService(format!(
+                "Listing Gemini models failed ({}): {}",
+                status, error_text
+            )));
+        }
+
+        let data: serde_json::Value = res.json().await.map_err(|e| {
+            AppError::ExternalService(format!("Failed to parse Gemini model list: {}", e))
+        })?;
+
+        for model in data["models"].as_array().into_iter().flatten() {
+            let Some(name) = model["name"].as_str() else {
+                continue;
+            };
+            models.push(ModelInfo {
+                name: name.trim_start_matches("models/").to_string(),
+                methods: model["supportedGenerationMethods"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|m| m.as_str().map(String::from))
+                    .collect(),
+            });
+        }
+
+        match data["nextPageToken"].as_str().filter(|t| !t.is_empty()) {
+            Some(token) => page_token = Some(token.to_string()),
+            None => break,
+        }
+    }
+
+    Ok(models)
+}
+
+/// Describe why Gemini refused to answer, if it did.
+/// Covers a blocked prompt (`promptFeedback.blockReason`) and a candidate stopped for safety.
+fn blocked_reason(data: &serde_json::Value) -> Option<String> {
+    let (reason, ratings) = if let Some(reason) = data["promptFeedback"]["blockReason"].as_str() {
+        (reason, &data["promptFeedback"]["safetyRatings"])
+    } else {
+        let candidate = &data["candidates"][0];
+        match candidate["finishReason"].as_str() {
+            Some(r @ ("SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST" | "SPII")) => {
+                (r, &candidate["safetyRatings"])
+            }
+            _ => return None,
+        }
+    };
+
+    let categories: Vec<String> = ratings
+        .as_array()
+        .map(|list| {
+            list.iter()
+                .filter(|r| r["blocked"].as_bool() == Some(true) || r["probability"] == "HIGH")
+                .filter_map(|r| r["category"].as_str())
+                .map(|c| c.trim_start_matches("HARM_CATEGORY_").to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if categories.is_empty() {
+        Some(reason.to_string())
+    } else {
+        Some(format!("{} ({})", reason, categories.join(", ")))
+    }
+}
+
+/// A stderr spinner showing which model is being waited on.
+fn progress_spinner(enabled: bool, model: &str) -> ProgressBar {
+    if !enabled {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template("{spinner} {msg} ({elapsed})") {
+        spinner.set_style(style);
+    }
+    spinner.set_message(format!("Waiting for {}...", model));
+    spinner.enable_steady_tick(Duration::from_millis(120));
+    spinner
+}
+
+/// A comment's top reply, indented under it and marked so the model reads it as a response.
+fn reply_line(reply: &Comment, body: &str, options: &PromptOptions) -> String {
+    format!(
+        "  - ↳ {}[Reply, score {}] {}\n",
+        comment_ref(reply, options),
+        reply.score,
+        body
+    )
+}
+
+/// `[c:id] ` when comment references are on, otherwise empty.
+fn comment_ref(comment: &Comment, options: &PromptOptions) -> String {
+    if options.comment_refs && !comment.id.is_empty() {
+        format!("[c:{}] ", comment.id)
+    } else {
+        String::new()
+    }
+}
+
+pub fn build_prompt(post: &RedditPost, options: &PromptOptions, comment_summary: Option<&str>) -> String {
+    // The persona goes separately, as the payload's systemInstruction
+    let mut prompt = String::new();
+
+    if options.aggregate {
+        prompt.push_str(
+            "Analyze the following collection of Reddit threads from one community and identify \
+             pain points, frustrations, and unmet needs that recur across several threads. \
+             Favor patterns over one-off complaints.\n\n",
+        );
+    } else if options.comments_only {
+        prompt.push_str(
+            "Analyze the comments of the following Reddit discussion and identify concrete pain points, \
+             frustrations, unmet needs, or repeated patterns. The post title is only context; \
+             base every idea on what commenters said.\n\n",
+        );
+    } else {
+        prompt.push_str(
+            "Analyze the following Reddit discussion (post + comments) and identify concrete pain points, \
+             frustrations, unmet needs, or repeated patterns.\n\n",
+        );
+    }
+
+    prompt.push_str(
+        "Your task is to generate 3 highly practical micro-SaaS or small product ideas that:\n\n\
          - Can be built by a solo developer or small team\n\
          - Are realistic and narrowly scoped\n\
          - Solve a specific pain point from the discussion\n\
@@ -131,13 +705,80 @@ fn build_prompt(post: &RedditPost) -> String {
          Reddit Discussion:\n\n",
     );
 
+    if let Some((subreddit, description)) = &options.community {
+        prompt.push_str(&format!(
+            "Community: r/{} ({})\nKeep ideas relevant to the people in this community.\n\n",
+            subreddit, description
+        ));
+    }
+
     prompt.push_str(&format!("Title:\n{}\n\n", post.title));
-    prompt.push_str(&format!("Body:\n{}\n\n", post.body));
+    if !options.comments_only {
+        prompt.push_str(&format!("Body:\n{}\n\n", post.body));
+    }
 
-    if !post.comments.is_empty() {
-        prompt.push_str("Top Comments:\n");
+    if let Some(summary) = comment_summary {
+        prompt.push_str(&format!(
+            "Comment Summary (key points from {} comments):\n{}\n",
+            post.comments.len(),
+            summary.trim()
+        ));
+    } else if post.focused_comment.is_some() && !post.comments.is_empty() {
+        // A linked subthread reads in order; ranking would separate replies from their parents
+        prompt.push_str(
+            "Comment Thread (the user linked this subthread, so focus on what it discusses; \
+             replies are indented under the comment they answer):\n",
+        );
+        let root_depth = post.comments[0].depth;
         for comment in &post.comments {
-            prompt.push_str(&format!("- {}\n", comment));
+            let indent = "  ".repeat(comment.depth.saturating_sub(root_depth) as usize);
+            let body = match options.comment_strategy {
+                CommentStrategy::TopQuotes => analysis::top_sentence(&comment.body),
+                _ => comment.body.as_str(),
+            };
+            prompt.push_str(&format!(
+                "{}- {}[score {}] {}\n",
+                indent,
+                comment_ref(comment, options),
+                comment.score,
+                body
+            ));
+        }
+    } else if !post.comments.is_empty() {
+        prompt.push_str("Top Comments (most endorsed first):\n");
+        for (i, comment) in rank_comments(&post.comments, &options.weighting).iter().enumerate() {
+            let reference = comment_ref(comment, options);
+            let body = match options.comment_strategy {
+                CommentStrategy::TopQuotes => analysis::top_sentence(&comment.body),
+                _ => comment.body.as_str(),
+            };
+            if i < HIGHLIGHTED_COMMENTS && comment.score > 0 {
+                prompt.push_str(&format!(
+                    "- {}[Most upvoted, score {}] {}\n",
+                    reference, comment.score, body
+                ));
+            } else {
+                prompt.push_str(&format!("- {}{}\n", reference, body));
+            }
+            if let Some(reply) = &comment.top_reply {
+                let body = match options.comment_strategy {
+                    CommentStrategy::TopQuotes => analysis::top_sentence(&reply.body),
+                    _ => reply.body.as_str(),
+                };
+                prompt.push_str(&reply_line(reply, body, options));
+            }
+        }
+        if post.comments.iter().any(|c| c.top_reply.is_some()) {
+            prompt.push_str("Lines marked ↳ are the top reply to the comment above them.\n");
+        }
+    }
+
+    if !post.comments.is_empty() {
+        if options.comment_refs {
+            prompt.push_str(
+                "When an idea is inspired by a specific comment, start its source_evidence \
+                 with that comment's [c:id] reference.\n",
+            );
         }
         prompt.push('\n');
     }
@@ -150,8 +791,81 @@ fn build_prompt(post: &RedditPost) -> String {
          - \"core_problem\": string\n\
          - \"mvp_features\": array of strings (3-6 items)\n\
          - \"monetization\": string\n\
-         - \"feasibility\": string\n",
+         - \"feasibility\": string\n\
+         - \"effort_estimate\": one of \"weekend\", \"1-2 weeks\", \"1-3 months\", \"3+ months\" \
+           (time for one developer to ship the MVP)\n\
+         - \"source_evidence\": string (a short quote or close paraphrase of the part of the post \
+           or the comment that inspired the idea)\n",
     );
+    if !options.categories.is_empty() {
+        let quoted: Vec<String> = options.categories.iter().map(|c| format!("\"{}\"", c)).collect();
+        prompt.push_str(&format!(
+            "- \"category\": one of {} (the single best fit)\n",
+            quoted.join(", ")
+        ));
+    }
+
+    if !options.guidance.is_empty() {
+        prompt.push_str("\nAdditional requirements for every idea:\n");
+        for line in &options.guidance {
+            prompt.push_str(&format!("- {}\n", line.trim()));
+        }
+    }
+
+    if let Some(top_up) = &options.top_up {
+        prompt.push_str(&format!(
+            "\nIMPORTANT: Ignore the count above. Generate exactly {} additional idea(s) that are \
+             clearly different from these already proposed: {}.\n",
+            top_up.count,
+            top_up.existing.join(", ")
+        ));
+    }
+
+    if let Some(language) = options.language.filter(|l| *l != "English") {
+        prompt.push_str(&format!(
+            "\nWrite every field value in {}. Keep the JSON field names exactly as listed above, in English.\n",
+            language
+        ));
+    }
+
+    if options.strict_json {
+        prompt.push_str(
+            "\nIMPORTANT: A previous reply could not be parsed. Your entire response must be a single \
+             valid JSON array starting with '[' and ending with ']'. Do not include prose, headings, \
+             or code fences.\n",
+        );
+    }
 
     prompt
 }
+
+/// Order comments by descending weight so the model anchors on community consensus.
+fn rank_comments<'a>(comments: &'a [Comment], weighting: &CommentWeighting) -> Vec<&'a Comment> {
+    let mut ranked: Vec<&Comment> = comments.iter().collect();
+    ranked.sort_by(|a, b| {
+        weighting
+            .weight(b.score, b.depth)
+            .total_cmp(&weighting.weight(a.score, a.depth))
+    });
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalation_only_steps_up() {
+        assert_eq!(
+            escalation_model(Some("gemini-2.5-flash"), None).as_deref(),
+            Some(ESCALATION_MODEL)
+        );
+        assert_eq!(escalation_model(Some(ESCALATION_MODEL), None), None);
+        // Designating a lighter model in the chain is no escalation
+        assert_eq!(escalation_model(Some("gemini-2.5-flash"), Some("gemini-2.0-flash")), None);
+        assert_eq!(
+            escalation_model(Some("gemini-2.0-flash"), Some("gemini-2.5-flash")).as_deref(),
+            Some("gemini-2.5-flash")
+        );
+    }
+}
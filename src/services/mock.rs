@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use futures::future::BoxFuture;
+
+use crate::errors::AppError;
+use crate::models::{Idea, RedditPost};
+use crate::services::gemini::{self, IdeaGenerator, PromptOptions, Reply};
+use crate::services::reddit::{self, CommentLimits, ListedPost, RedditClient};
+
+/// Default fixture directory for `--mock`, relative to the working directory.
+pub const DEFAULT_FIXTURES_DIR: &str = "fixtures";
+
+/// Canned Reddit and Gemini responses read from disk in place of network calls.
+///
+/// Reddit fixtures are raw API responses and go through the same parsing as live data,
+/// so comment limits and listing handling behave exactly as they would against Reddit.
+#[derive(Clone, Debug)]
+pub struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Fixtures { dir: dir.into() }
+    }
+
+    fn read(&self, name: &str) -> Result<String, AppError> {
+        let path = self.dir.join(name);
+        std::fs::read_to_string(&path)
+            .map_err(|e| AppError::Io(format!("Failed to read fixture {}: {}", path.display(), e)))
+    }
+}
+
+impl RedditClient for Fixtures {
+    /// `reddit_post.json`, whatever the requested URL; parsing labels it with that URL.
+    fn fetch_post_json<'a>(
+        &'a self,
+        _url: &'a str,
+        _limits: &'a CommentLimits,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move { self.read("reddit_post.json") })
+    }
+
+    /// `subreddit_listing.json`, capped at `limit` posts.
+    fn fetch_listing<'a>(
+        &'a self,
+        subreddit: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedPost>, AppError>> {
+        Box::pin(async move {
+            let text = self.read("subreddit_listing.json")?;
+            let mut posts = reddit::parse_listing(subreddit, &text)?;
+            posts.truncate(limit);
+            Ok(posts)
+        })
+    }
+
+    /// `subreddit_about.json`.
+    fn fetch_description<'a>(
+        &'a self,
+        subreddit: &'a str,
+    ) -> BoxFuture<'a, Result<Option<String>, AppError>> {
+        Box::pin(async move {
+            let text = self.read("subreddit_about.json")?;
+            reddit::parse_description(subreddit, &text)
+        })
+    }
+
+    /// `subreddit_search.json`, capped at `limit` names.
+    fn search_subreddits<'a>(
+        &'a self,
+        _query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<String>, AppError>> {
+        Box::pin(async move {
+            let text = self.read("subreddit_search.json")?;
+            let mut names = reddit::parse_subreddit_search(&text)?;
+            names.truncate(limit);
+            Ok(names)
+        })
+    }
+}
+
+impl IdeaGenerator for Fixtures {
+    /// `gemini_reply.txt`, returned as if it were the model's text output. The prompt is
+    /// built as the live client would, minus any comment summary.
+    fn generate<'a>(
+        &'a self,
+        post: &'a RedditPost,
+        options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<Reply, AppError>> {
+        Box::pin(async move {
+            Ok(Reply {
+                text: self.read("gemini_reply.txt")?,
+                prompt: gemini::build_prompt(post, options, None),
+                model: None,
+            })
+        })
+    }
+
+    /// `gemini_scores.txt`, whatever ideas were sent.
+    fn score<'a>(
+        &'a self,
+        _post: &'a RedditPost,
+        _ideas: &'a [Idea],
+        _options: &'a PromptOptions,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move { self.read("gemini_scores.txt") })
+    }
+}
@@ -1,2 +1,3 @@
 pub mod gemini;
+pub mod mock;
 pub mod reddit;
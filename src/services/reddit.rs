@@ -1,88 +1,233 @@
+use futures::future::BoxFuture;
+
+use crate::cli::CommentSort;
 use crate::errors::AppError;
-use crate::models::RedditPost;
+use crate::models::{Comment, RedditPost};
+use crate::utils::backoff::{retry_async, BackoffPolicy};
+use crate::utils::throttle;
 
-const USER_AGENT: &str =
+/// Default User-Agent, used when `REDDIT_USER_AGENT` is not set.
+pub const DEFAULT_USER_AGENT: &str =
     "reddit-research-engine:v0.1.0 (by /u/reddit-research-bot)";
 
 const REDDIT_BASE: &str = "https://old.reddit.com";
 
-/// Fetches a Reddit post including top-level comments.
-pub async fn fetch_reddit_post(
+/// Which comments are pulled into a post and how much of them. Whichever cap is hit first wins.
+#[derive(Clone, Debug)]
+pub struct CommentLimits {
+    pub max_comments: usize,
+    /// Stop adding comments once their combined length would exceed this many characters.
+    pub max_chars: Option<usize>,
+    /// Longer comments are truncated to this many characters with an ellipsis.
+    pub max_comment_length: usize,
+    /// Sort order requested from Reddit; `None` uses Reddit's default.
+    pub sort: Option<CommentSort>,
+    /// Comments scored below this are skipped and don't count toward `max_comments`.
+    pub min_score: Option<i64>,
+    /// Also take the highest-scored direct reply under each comment (one level deep).
+    pub top_replies: bool,
+}
+
+/// Where posts and subreddit listings come from. Implemented by the live API client
+/// ([`HttpReddit`]) and by `--mock` fixtures, so the pipeline can run against either.
+pub trait RedditClient: Send + Sync {
+    /// The post's `.json` response body, unparsed.
+    fn fetch_post_json<'a>(
+        &'a self,
+        url: &'a str,
+        limits: &'a CommentLimits,
+    ) -> BoxFuture<'a, Result<String, AppError>>;
+
+    fn fetch_post<'a>(
+        &'a self,
+        url: &'a str,
+        limits: &'a CommentLimits,
+    ) -> BoxFuture<'a, Result<RedditPost, AppError>> {
+        Box::pin(async move {
+            let text = self.fetch_post_json(url, limits).await?;
+            parse_post(url, &text, limits)
+        })
+    }
+
+    fn fetch_listing<'a>(
+        &'a self,
+        subreddit: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedPost>, AppError>>;
+
+    /// The subreddit's public description, or `None` if it has none.
+    fn fetch_description<'a>(
+        &'a self,
+        subreddit: &'a str,
+    ) -> BoxFuture<'a, Result<Option<String>, AppError>>;
+
+    /// Names of subreddits matching `query`, most relevant first.
+    fn search_subreddits<'a>(
+        &'a self,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<String>, AppError>>;
+}
+
+/// Reddit's public JSON API.
+pub struct HttpReddit<'c> {
+    pub client: &'c reqwest::Client,
+    pub user_agent: &'c str,
+}
+
+impl RedditClient for HttpReddit<'_> {
+    fn fetch_post_json<'a>(
+        &'a self,
+        url: &'a str,
+        limits: &'a CommentLimits,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(fetch_reddit_post_json(self.client, self.user_agent, url, limits))
+    }
+
+    fn fetch_listing<'a>(
+        &'a self,
+        subreddit: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedPost>, AppError>> {
+        Box::pin(fetch_subreddit_posts(self.client, self.user_agent, subreddit, limit))
+    }
+
+    fn fetch_description<'a>(
+        &'a self,
+        subreddit: &'a str,
+    ) -> BoxFuture<'a, Result<Option<String>, AppError>> {
+        Box::pin(fetch_subreddit_description(self.client, self.user_agent, subreddit))
+    }
+
+    fn search_subreddits<'a>(
+        &'a self,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<String>, AppError>> {
+        Box::pin(search_subreddits(self.client, self.user_agent, query, limit))
+    }
+}
+
+/// Fetches a Reddit post's JSON, including top-level comments. Parse it with [`parse_post`].
+pub async fn fetch_reddit_post_json(
     client: &reqwest::Client,
+    user_agent: &str,
     url: &str,
-    max_comments: usize,
-) -> Result<RedditPost, AppError> {
+    limits: &CommentLimits,
+) -> Result<String, AppError> {
     // Use old.reddit.com to avoid TLS-fingerprint-based blocking
-    let fetch_url = format!("{}.json", url.replace("www.reddit.com", "old.reddit.com"));
-
-    let response = client
-        .get(&fetch_url)
-        .header("User-Agent", USER_AGENT)
-        .header("Accept", "application/json")
-        .header("Accept-Language", "en-US,en;q=0.9")
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Reddit API error: {}", e);
-            AppError::ExternalService("Failed to contact Reddit. Check the URL.".into())
-        })?;
-
-    let text = response.text().await.map_err(|e| {
-        eprintln!("Failed reading Reddit response text: {}", e);
-        AppError::ExternalService("Could not read Reddit response.".into())
-    })?;
+    let mut fetch_url = format!("{}.json", url.replace("www.reddit.com", "old.reddit.com"));
+    if let Some(sort) = limits.sort {
+        fetch_url.push_str(&format!("?sort={}", sort.as_param()));
+    }
 
-    let data: serde_json::Value = serde_json::from_str(&text).map_err(|_| {
+    get_with_retry(client, user_agent, &fetch_url).await.map_err(|e| {
+        eprintln!("Reddit API error: {}", e);
+        AppError::ExternalService("Failed to contact Reddit. Check the URL.".into())
+    })
+}
+
+/// Build a post from the body of a Reddit `<post>.json` response.
+pub fn parse_post(url: &str, text: &str, limits: &CommentLimits) -> Result<RedditPost, AppError> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|_| {
         eprintln!("Reddit did not return JSON. Raw response:\n{}", text);
         AppError::ExternalService(
             "Reddit did not return valid JSON. The post may be private, removed, or NSFW.".into(),
         )
     })?;
 
+    // A post returns [post listing, comment listing]; anything else (a subreddit
+    // listing, a wiki page) would otherwise silently produce "No title"
+    let is_post = data.as_array().is_some_and(|a| a.len() == 2)
+        && data[0]["data"]["children"][0]["kind"].as_str() == Some("t3");
+    if !is_post {
+        let kind = data["kind"].as_str().unwrap_or("unknown");
+        return Err(AppError::InvalidInput(format!(
+            "{} returned a '{}' page, not a post. To analyze a subreddit's hot posts, use \
+             `subreddit <name>` instead.",
+            url, kind
+        )));
+    }
+
     // Extract post data from [0]
     let post_data = &data[0]["data"]["children"][0]["data"];
     let title = post_data["title"].as_str().unwrap_or("No title").to_string();
     let body = post_data["selftext"].as_str().unwrap_or("No text").to_string();
+    let image_url = image_url(post_data);
 
-    // Extract top-level comments from [1]
-    let comments = extract_comments(&data[1], max_comments);
+    // Extract comments from [1]: the top-level ones, or the linked comment's subthread
+    let focused_comment = focused_comment_id(url);
+    let comments = if focused_comment.is_some() {
+        extract_thread(&data[1], limits)
+    } else {
+        extract_comments(&data[1], limits)
+    };
 
     Ok(RedditPost {
         url: url.to_string(),
         title,
         body,
         comments,
+        image_url,
+        score: post_data["score"].as_i64().unwrap_or(0),
+        num_comments: post_data["num_comments"].as_u64().unwrap_or(0),
+        focused_comment,
     })
 }
 
-/// Fetches hot post URLs from a subreddit.
+/// The comment id in a comment permalink (`/comments/<post>/<slug>/<comment>/`), if the URL is one.
+pub fn focused_comment_id(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let mut segments = path.split('/').skip_while(|s| *s != "comments").skip(3);
+    segments
+        .next()
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(str::to_string)
+}
+
+/// The linked image of an image post, detected from Reddit's `post_hint` or the link's extension.
+fn image_url(post_data: &serde_json::Value) -> Option<String> {
+    let url = post_data["url_overridden_by_dest"]
+        .as_str()
+        .or_else(|| post_data["url"].as_str())?;
+    let path = url.split('?').next().unwrap_or(url).to_lowercase();
+    let is_image = post_data["post_hint"].as_str() == Some("image")
+        || [".jpg", ".jpeg", ".png", ".webp"].iter().any(|ext| path.ends_with(ext));
+    is_image.then(|| url.to_string())
+}
+
+/// A post from a subreddit listing, with the subreddit it was actually posted in.
+#[derive(Clone, Debug)]
+pub struct ListedPost {
+    pub url: String,
+    pub subreddit: String,
+    /// A text (self) post rather than a link to an external page.
+    pub is_self: bool,
+}
+
+/// Fetches hot posts from a subreddit. Also works for `all` and `popular`.
 pub async fn fetch_subreddit_posts(
     client: &reqwest::Client,
+    user_agent: &str,
     subreddit: &str,
     limit: usize,
-) -> Result<Vec<String>, AppError> {
+) -> Result<Vec<ListedPost>, AppError> {
     let url = format!(
         "{}/r/{}/hot.json?limit={}",
         REDDIT_BASE, subreddit, limit
     );
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", USER_AGENT)
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Subreddit fetch error: {}", e);
-            AppError::ExternalService(format!("Failed to fetch r/{}", subreddit))
-        })?;
-
-    let text = response.text().await.map_err(|e| {
-        eprintln!("Failed reading subreddit response: {}", e);
-        AppError::ExternalService("Could not read subreddit response.".into())
+    let text = get_with_retry(client, user_agent, &url).await.map_err(|e| {
+        eprintln!("Subreddit fetch error: {}", e);
+        AppError::ExternalService(format!("Failed to fetch r/{}", subreddit))
     })?;
 
-    let data: serde_json::Value = serde_json::from_str(&text).map_err(|_| {
+    parse_listing(subreddit, &text)
+}
+
+/// Read post URLs from the body of a subreddit listing response.
+pub fn parse_listing(subreddit: &str, text: &str) -> Result<Vec<ListedPost>, AppError> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|_| {
         AppError::ExternalService(format!("r/{} did not return valid JSON.", subreddit))
     })?;
 
@@ -90,34 +235,318 @@ pub async fn fetch_subreddit_posts(
         .as_array()
         .ok_or_else(|| AppError::ExternalService("Unexpected subreddit JSON structure.".into()))?;
 
-    let urls: Vec<String> = children
+    let posts: Vec<ListedPost> = children
         .iter()
         .filter_map(|child| {
             let permalink = child["data"]["permalink"].as_str()?;
-            Some(format!("{}{}", REDDIT_BASE, permalink.trim_end_matches('/')))
+            Some(ListedPost {
+                url: format!("{}{}", REDDIT_BASE, permalink.trim_end_matches('/')),
+                // Aggregate feeds like r/all mix subreddits, so take each post's own
+                subreddit: child["data"]["subreddit"]
+                    .as_str()
+                    .unwrap_or(subreddit)
+                    .to_string(),
+                is_self: child["data"]["is_self"].as_bool().unwrap_or(true),
+            })
         })
         .collect();
 
-    Ok(urls)
+    Ok(posts)
 }
 
-fn extract_comments(comment_listing: &serde_json::Value, max: usize) -> Vec<String> {
+/// Fetches a subreddit's `public_description` from its about page.
+pub async fn fetch_subreddit_description(
+    client: &reqwest::Client,
+    user_agent: &str,
+    subreddit: &str,
+) -> Result<Option<String>, AppError> {
+    let url = format!("{}/r/{}/about.json", REDDIT_BASE, subreddit);
+
+    let text = get_with_retry(client, user_agent, &url).await.map_err(|e| {
+        eprintln!("Subreddit about fetch error: {}", e);
+        AppError::ExternalService(format!("Failed to fetch r/{} about page", subreddit))
+    })?;
+
+    parse_description(subreddit, &text)
+}
+
+/// Read `public_description` from the body of an `about.json` response.
+pub fn parse_description(subreddit: &str, text: &str) -> Result<Option<String>, AppError> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|_| {
+        AppError::ExternalService(format!("r/{} about page did not return valid JSON.", subreddit))
+    })?;
+
+    Ok(data["data"]["public_description"]
+        .as_str()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(String::from))
+}
+
+/// Searches subreddit names and descriptions via `/subreddits/search`.
+pub async fn search_subreddits(
+    client: &reqwest::Client,
+    user_agent: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<String>, AppError> {
+    let url = format!(
+        "{}/subreddits/search.json?q={}&limit={}",
+        REDDIT_BASE, query, limit
+    );
+
+    let text = get_with_retry(client, user_agent, &url).await.map_err(|e| {
+        eprintln!("Subreddit search error: {}", e);
+        AppError::ExternalService(format!("Failed to search for subreddits like r/{}", query))
+    })?;
+
+    parse_subreddit_search(&text)
+}
+
+/// Read subreddit names from the body of a `/subreddits/search` response.
+pub fn parse_subreddit_search(text: &str) -> Result<Vec<String>, AppError> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|_| {
+        AppError::ExternalService("Subreddit search did not return valid JSON.".into())
+    })?;
+
+    Ok(data["data"]["children"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|child| child["data"]["display_name"].as_str())
+        .map(String::from)
+        .collect())
+}
+
+/// GET a Reddit JSON endpoint, retrying network errors, 429s, and 5xx responses with backoff.
+async fn get_with_retry(
+    client: &reqwest::Client,
+    user_agent: &str,
+    url: &str,
+) -> Result<String, String> {
+    retry_async(&BackoffPolicy::default(), || async {
+        // Taken per attempt, so a request waiting out its backoff doesn't hold a slot
+        let _slot = throttle::reddit_slot().await;
+        let response = client
+            .get(url)
+            .header("User-Agent", user_agent)
+            .header("Accept", "application/json")
+            .header("Accept-Language", "en-US,en;q=0.9")
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+        let status = response.status();
+        if status == 429 {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(std::time::Duration::from_secs);
+            throttle::reddit_rate_limited(retry_after);
+        }
+        if status == 429 || status.is_server_error() {
+            return Err(format!("Reddit returned {} for {}", status, url));
+        }
+
+        response
+            .text()
+            .await
+            .map_err(|e| format!("Failed reading response from {}: {}", url, e))
+    })
+    .await
+}
+
+fn extract_comments(comment_listing: &serde_json::Value, limits: &CommentLimits) -> Vec<Comment> {
     let Some(children) = comment_listing["data"]["children"].as_array() else {
         return Vec::new();
     };
 
-    children
+    let mut comments = Vec::new();
+    let mut total_chars = 0;
+
+    for c in children.iter().filter(|c| c["kind"].as_str() == Some("t1")) {
+        if comments.len() >= limits.max_comments {
+            break;
+        }
+        let Some(mut comment) = parse_comment(c, limits) else {
+            continue;
+        };
+
+        let len = comment.body.chars().count();
+        if let Some(budget) = limits.max_chars
+            && total_chars + len > budget
+        {
+            break;
+        }
+        total_chars += len;
+
+        if limits.top_replies {
+            // A reply that would overrun the budget is left off; its parent still counts
+            comment.top_reply = top_reply(c, limits)
+                .filter(|r| {
+                    let len = r.body.chars().count();
+                    limits.max_chars.is_none_or(|budget| total_chars + len <= budget)
+                })
+                .map(|r| {
+                    total_chars += r.body.chars().count();
+                    Box::new(r)
+                });
+        }
+        comments.push(comment);
+    }
+
+    comments
+}
+
+/// Comments of a permalinked subthread, depth-first so each reply follows its parent.
+/// Replies under a deleted comment are still kept. Stops at the same caps as [`extract_comments`].
+fn extract_thread(comment_listing: &serde_json::Value, limits: &CommentLimits) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut total_chars = 0;
+    let mut stack: Vec<&serde_json::Value> = match comment_listing["data"]["children"].as_array() {
+        Some(children) => children.iter().rev().collect(),
+        None => return comments,
+    };
+
+    while let Some(c) = stack.pop() {
+        if comments.len() >= limits.max_comments {
+            break;
+        }
+        if c["kind"].as_str() != Some("t1") {
+            continue;
+        }
+        if let Some(replies) = c["data"]["replies"]["data"]["children"].as_array() {
+            stack.extend(replies.iter().rev());
+        }
+        let Some(comment) = parse_comment(c, limits) else {
+            continue;
+        };
+        let len = comment.body.chars().count();
+        if let Some(budget) = limits.max_chars
+            && total_chars + len > budget
+        {
+            break;
+        }
+        total_chars += len;
+        comments.push(comment);
+    }
+
+    comments
+}
+
+/// One comment from a listing child, or `None` if it is deleted, removed, or under `min_score`.
+fn parse_comment(c: &serde_json::Value, limits: &CommentLimits) -> Option<Comment> {
+    let body = c["data"]["body"].as_str()?;
+    let author = c["data"]["author"].as_str().unwrap_or("");
+    // Skip deleted/removed comments
+    if author == "[deleted]" || body == "[deleted]" || body == "[removed]" {
+        return None;
+    }
+    let score = c["data"]["score"].as_i64().unwrap_or(0);
+    if limits.min_score.is_some_and(|min| score < min) {
+        return None;
+    }
+
+    let permalink = c["data"]["permalink"]
+        .as_str()
+        .map(|p| format!("{}{}", REDDIT_BASE, p))
+        .unwrap_or_default();
+
+    Some(Comment {
+        id: c["data"]["id"].as_str().unwrap_or("").to_string(),
+        permalink,
+        body: truncate_chars(body, limits.max_comment_length),
+        score,
+        depth: c["data"]["depth"].as_u64().unwrap_or(0) as u32,
+        top_reply: None,
+    })
+}
+
+/// The highest-scored usable direct reply to comment `c`. Reddit sends `replies` as an
+/// empty string when there are none.
+fn top_reply(c: &serde_json::Value, limits: &CommentLimits) -> Option<Comment> {
+    c["data"]["replies"]["data"]["children"]
+        .as_array()?
         .iter()
-        .filter(|c| c["kind"].as_str() == Some("t1"))
-        .filter_map(|c| {
-            let body = c["data"]["body"].as_str()?;
-            let author = c["data"]["author"].as_str().unwrap_or("");
-            // Skip deleted/removed comments
-            if author == "[deleted]" || body == "[deleted]" || body == "[removed]" {
-                return None;
-            }
-            Some(body.to_string())
-        })
-        .take(max)
-        .collect()
+        .filter(|r| r["kind"].as_str() == Some("t1"))
+        .filter_map(|r| parse_comment(r, limits))
+        .reduce(|best, r| if r.score > best.score { r } else { best })
+}
+
+/// Cut `text` to at most `max` characters, marking the cut with an ellipsis.
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> CommentLimits {
+        CommentLimits {
+            max_comments: 10,
+            max_chars: None,
+            max_comment_length: 50,
+            sort: None,
+            min_score: None,
+            top_replies: false,
+        }
+    }
+
+    fn listing(bodies: &[&str]) -> serde_json::Value {
+        let children: Vec<serde_json::Value> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| {
+                serde_json::json!({
+                    "kind": "t1",
+                    "data": { "id": format!("c{}", i), "author": "someone", "body": body, "score": 1 }
+                })
+            })
+            .collect();
+        serde_json::json!({ "data": { "children": children } })
+    }
+
+    #[test]
+    fn truncate_chars_cuts_on_char_boundaries() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate_chars("hello world", 6), "hello…");
+        assert_eq!(truncate_chars("naïve café ☕☕☕", 12), "naïve café ☕…");
+    }
+
+    #[test]
+    fn huge_comments_are_truncated_and_short_ones_kept_whole() {
+        let huge = "x".repeat(10_000);
+        let listing = listing(&["Short one.", &huge, "Another short comment.", &huge]);
+        let comments = extract_comments(&listing, &limits());
+
+        assert_eq!(comments.len(), 4);
+        assert_eq!(comments[0].body, "Short one.");
+        assert_eq!(comments[2].body, "Another short comment.");
+        for long in [&comments[1], &comments[3]] {
+            assert_eq!(long.body.chars().count(), 51);
+            assert!(long.body.ends_with('…'));
+        }
+    }
+
+    #[test]
+    fn char_budget_counts_truncated_lengths() {
+        let huge = "y".repeat(10_000);
+        let listing = listing(&[&huge, "tiny", &huge, "also tiny"]);
+        // Two truncated comments (51 chars each) and one short one fit in 110 characters
+        let limits = CommentLimits {
+            max_chars: Some(110),
+            ..limits()
+        };
+        let comments = extract_comments(&listing, &limits);
+
+        assert_eq!(comments.len(), 3);
+        assert_eq!(comments[1].body, "tiny");
+    }
 }
@@ -0,0 +1,208 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Retries left for the whole run, shared by every `retry_async` call. Unlimited by default.
+static RETRY_BUDGET: AtomicUsize = AtomicUsize::new(usize::MAX);
+static BUDGET_EXHAUSTED_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Cap the total number of retries across the run (`--retry-budget`).
+pub fn set_retry_budget(retries: usize) {
+    RETRY_BUDGET.store(retries, Ordering::SeqCst);
+}
+
+/// Take one retry from the run-wide budget. Returns false once it is spent.
+fn take_retry() -> bool {
+    RETRY_BUDGET
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+        .is_ok()
+}
+
+/// How many times to try an operation and how long to wait between attempts.
+#[derive(Clone, Debug)]
+pub struct BackoffPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Randomize each delay between zero and its exponential value ("full jitter").
+    pub jitter: bool,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        BackoffPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter: true,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Delay before retry number `attempt` (1-based): `base * 2^(attempt-1)`, capped at `max_delay`.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if self.jitter {
+            delay.mul_f64(jitter_fraction())
+        } else {
+            delay
+        }
+    }
+}
+
+/// A failed attempt, marked as worth retrying or not.
+pub enum Attempt<E> {
+    /// Might succeed if tried again: a network error, 429, or 5xx.
+    Transient(E),
+    /// Will fail the same way every time, e.g. a 400 or 403; returned without retrying.
+    Permanent(E),
+}
+
+/// Run `f` until it succeeds or `policy.max_attempts` is reached, sleeping between attempts.
+/// Every error returned by `f` is treated as transient, so keep permanent failures outside it,
+/// or use [`retry_transient`].
+pub async fn retry_async<F, Fut, T, E>(policy: &BackoffPolicy, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    retry_transient(policy, || {
+        let attempt = f();
+        async move { attempt.await.map_err(Attempt::Transient) }
+    })
+    .await
+}
+
+/// Like [`retry_async`], but `f` says which failures are worth another attempt; a permanent
+/// one is returned at once. Each retry also draws on the run-wide budget; once that is spent,
+/// the first error is returned.
+pub async fn retry_transient<F, Fut, T, E>(policy: &BackoffPolicy, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Attempt<E>>>,
+    E: Display,
+{
+    let mut attempt = 1;
+    loop {
+        let e = match f().await {
+            Ok(value) => return Ok(value),
+            Err(Attempt::Permanent(e)) => return Err(e),
+            Err(Attempt::Transient(e)) => e,
+        };
+        match e {
+            e if attempt >= policy.max_attempts => return Err(e),
+            e if !take_retry() => {
+                if !BUDGET_EXHAUSTED_WARNED.swap(true, Ordering::SeqCst) {
+                    eprintln!("⚠️  Retry budget exhausted; failing fast from now on");
+                }
+                return Err(e);
+            }
+            e => {
+                let delay = policy.delay_for(attempt);
+                eprintln!(
+                    "{} — retrying in {:.1}s (attempt {}/{})",
+                    e,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    policy.max_attempts
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// A value in [0, 1) from the clock's sub-second nanos. Good enough to spread retries apart.
+fn jitter_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    nanos as f64 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+    use tokio::time::Instant;
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn delay_doubles_and_caps() {
+        let policy = BackoffPolicy {
+            max_delay: Duration::from_secs(3),
+            ..policy()
+        };
+        assert_eq!(policy.delay_for(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_for(2), Duration::from_secs(1));
+        assert_eq!(policy.delay_for(3), Duration::from_secs(2));
+        assert_eq!(policy.delay_for(4), Duration::from_secs(3));
+        assert_eq!(policy.delay_for(40), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn jitter_stays_below_the_exponential_delay() {
+        let policy = BackoffPolicy { jitter: true, ..policy() };
+        for attempt in 1..=5 {
+            assert!(policy.delay_for(attempt) <= BackoffPolicy { jitter: false, ..policy.clone() }.delay_for(attempt));
+        }
+    }
+
+    // The paused clock auto-advances through each sleep, so elapsed time is exactly the backoff
+    #[tokio::test(start_paused = true)]
+    async fn retries_transient_failures_until_success() {
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        let result: Result<&str, String> = retry_async(&policy(), || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("503".to_string()),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+        assert_eq!(result, Ok("done"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        let result: Result<(), String> = retry_async(&policy(), || async {
+            Err(format!("failure {}", calls.fetch_add(1, Ordering::SeqCst) + 1))
+        })
+        .await;
+        assert_eq!(result, Err("failure 3".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_failures_are_not_retried() {
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        let result: Result<(), String> = retry_transient(&policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(Attempt::Permanent("403".to_string()))
+        })
+        .await;
+        assert_eq!(result, Err("403".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,160 @@
+/// Common short words per Latin-script language, for telling them apart by frequency.
+/// Languages beyond the `--lang` set are listed too, so a Polish or Swedish post is
+/// recognized as such rather than waved through as "unclear".
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "to", "of", "that", "it", "for", "with", "you", "this", "are", "was", "have", "but", "not", "my", "what"]),
+    ("es", &["el", "la", "los", "las", "que", "de", "y", "es", "en", "por", "para", "con", "una", "pero", "como", "mi", "muy", "sí"]),
+    ("fr", &["le", "la", "les", "et", "est", "de", "des", "que", "un", "une", "pour", "pas", "je", "vous", "avec", "dans", "mais", "sur"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ich", "ein", "eine", "mit", "zu", "auf", "für", "aber", "wie", "sie", "es", "den"]),
+    ("pt", &["o", "os", "as", "que", "de", "e", "é", "não", "um", "uma", "para", "com", "mas", "como", "em", "eu", "você", "do"]),
+    ("it", &["il", "lo", "gli", "che", "di", "e", "è", "non", "un", "una", "per", "con", "ma", "come", "sono", "io", "della", "anche"]),
+    ("nl", &["de", "het", "een", "en", "is", "niet", "ik", "van", "dat", "met", "voor", "op", "maar", "zijn", "je", "wat", "ook", "naar"]),
+    ("pl", &["i", "w", "nie", "na", "się", "jest", "że", "z", "do", "jak", "ale", "co", "mam", "czy", "jestem", "tak", "już", "dla"]),
+    ("sv", &["och", "att", "det", "som", "är", "en", "på", "för", "med", "jag", "inte", "har", "till", "av", "men", "om", "kan", "vi"]),
+    ("da", &["og", "at", "det", "som", "er", "en", "på", "for", "med", "jeg", "ikke", "har", "til", "af", "men", "om", "kan", "den"]),
+    ("fi", &["ja", "on", "ei", "että", "se", "mutta", "kun", "minä", "olen", "tämä", "myös", "niin", "kuin", "mitä", "jos", "ole", "oli", "sen"]),
+    ("tr", &["ve", "bir", "bu", "için", "ile", "ne", "ama", "çok", "daha", "gibi", "ben", "var", "yok", "mi", "olarak", "değil", "da", "sonra"]),
+    ("cs", &["a", "je", "se", "na", "že", "v", "ne", "jsem", "ale", "jak", "s", "pro", "by", "co", "tak", "už", "jako", "když"]),
+    ("ro", &["și", "este", "că", "nu", "în", "la", "cu", "pe", "să", "mai", "dar", "ce", "pentru", "am", "sunt", "care", "din", "foarte"]),
+    ("id", &["yang", "dan", "di", "ini", "itu", "tidak", "saya", "ada", "dengan", "untuk", "ke", "dari", "akan", "juga", "bisa", "sudah", "kita", "apa"]),
+];
+
+/// Scripts that each belong to one `--lang` language.
+const LANGUAGE_SCRIPTS: &[(&str, &[(u32, u32)])] = &[
+    ("ko", &[(0xAC00, 0xD7AF), (0x1100, 0x11FF)]),
+    ("zh", &[(0x4E00, 0x9FFF), (0x3400, 0x4DBF)]),
+    ("hi", &[(0x0900, 0x097F)]),
+];
+
+/// Other scripts, reported by name since one script can cover several languages.
+const OTHER_SCRIPTS: &[(&str, &[(u32, u32)])] = &[
+    ("Cyrillic", &[(0x0400, 0x052F)]),
+    ("Greek", &[(0x0370, 0x03FF)]),
+    ("Arabic", &[(0x0600, 0x06FF), (0x0750, 0x077F)]),
+    ("Hebrew", &[(0x0590, 0x05FF)]),
+    ("Thai", &[(0x0E00, 0x0E7F)]),
+    ("Bengali", &[(0x0980, 0x09FF)]),
+    ("Tamil", &[(0x0B80, 0x0BFF)]),
+];
+
+/// Fewer stopword hits than this and the text is too short to call.
+const MIN_HITS: usize = 3;
+
+/// Best guess at the language of `text`: a language code (`en`, `pl`, ...) or, for scripts
+/// shared by several languages, the script's name (`Cyrillic`). Either way, anything not in
+/// a `--lang-filter` list is off-language. `None` when the text is too short or mixed to
+/// tell, so callers can keep posts rather than drop them on a hunch.
+pub fn detect(text: &str) -> Option<&'static str> {
+    if let Some(code) = detect_script(text) {
+        return Some(code);
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(code, list)| (*code, words.iter().filter(|w| list.contains(&w.as_str())).count()))
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+
+    let (best, hits) = scores[0];
+    // A tie between the top two (say Spanish and Portuguese) is no answer
+    (hits >= MIN_HITS && hits > scores[1].1).then_some(best)
+}
+
+/// Text whose letters are mostly in one non-Latin script is decided by that script.
+fn detect_script(text: &str) -> Option<&'static str> {
+    let in_ranges = |c: u32, ranges: &[(u32, u32)]| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&c));
+    let scripts = || LANGUAGE_SCRIPTS.iter().chain(OTHER_SCRIPTS);
+
+    let (mut letters, mut kana, mut non_latin) = (0, 0, 0);
+    let mut counts = vec![0; LANGUAGE_SCRIPTS.len() + OTHER_SCRIPTS.len()];
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        let c = c as u32;
+        letters += 1;
+        // Latin, including the extended ranges with accented letters
+        if c <= 0x024F || (0x1E00..=0x1EFF).contains(&c) {
+            continue;
+        }
+        non_latin += 1;
+        if (0x3040..=0x30FF).contains(&c) {
+            kana += 1;
+        } else if let Some(i) = scripts().position(|(_, ranges)| in_ranges(c, ranges)) {
+            counts[i] += 1;
+        }
+    }
+    if letters == 0 {
+        return None;
+    }
+
+    // Japanese mixes kana into Han, so any real share of kana marks it as Japanese
+    let han = LANGUAGE_SCRIPTS.iter().position(|(code, _)| *code == "zh").map_or(0, |i| counts[i]);
+    if kana * 10 >= letters && (kana + han) * 2 >= letters {
+        return Some("ja");
+    }
+    if let Some(((name, _), _)) = scripts().zip(&counts).find(|(_, count)| **count * 2 >= letters) {
+        return Some(name);
+    }
+    // Mostly non-Latin, but no script the tables know by name
+    (non_latin * 2 >= letters).then_some("a non-Latin script")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_latin_languages_by_stopwords() {
+        assert_eq!(
+            detect("I have been trying to find a tool for this, but what I found is not good."),
+            Some("en")
+        );
+        assert_eq!(
+            detect("Estoy buscando una herramienta para mi negocio, pero no encuentro nada que funcione."),
+            Some("es")
+        );
+        assert_eq!(
+            detect("Ich suche ein Tool für mein Team, aber die meisten sind zu teuer und nicht gut."),
+            Some("de")
+        );
+    }
+
+    #[test]
+    fn detects_latin_languages_outside_the_lang_set() {
+        assert_eq!(
+            detect("Szukam narzędzia do faktur, ale nie wiem czy to jest dobry pomysł i co mam zrobić."),
+            Some("pl")
+        );
+        assert_eq!(
+            detect("Jag letar efter ett verktyg som kan hjälpa mig med fakturor, men det är inte lätt."),
+            Some("sv")
+        );
+    }
+
+    #[test]
+    fn detects_scripts_tied_to_one_language() {
+        assert_eq!(detect("請求書を管理するツールを探しています"), Some("ja"));
+        assert_eq!(detect("송장을 관리하는 도구를 찾고 있습니다"), Some("ko"));
+        assert_eq!(detect("我正在寻找管理发票的工具"), Some("zh"));
+        assert_eq!(detect("मैं चालान प्रबंधित करने के लिए एक टूल ढूंढ रहा हूं"), Some("hi"));
+    }
+
+    #[test]
+    fn names_shared_scripts() {
+        assert_eq!(detect("Ищу инструмент для учёта счетов"), Some("Cyrillic"));
+        assert_eq!(detect("أبحث عن أداة لإدارة الفواتير"), Some("Arabic"));
+        assert_eq!(detect("Ψάχνω ένα εργαλείο για τιμολόγια"), Some("Greek"));
+        assert_eq!(detect("ฉันกำลังมองหาเครื่องมือจัดการใบแจ้งหนี้"), Some("Thai"));
+    }
+
+    #[test]
+    fn leaves_short_or_empty_text_undecided() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("SaaS idea?"), None);
+        assert_eq!(detect("12345 !!!"), None);
+    }
+}
@@ -1 +1,6 @@
+pub mod backoff;
+pub mod language;
+pub mod profile;
+pub mod sampling;
+pub mod throttle;
 pub mod validation;
@@ -0,0 +1,120 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Whether `--profile` is on. Timings are not recorded otherwise.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Time spent per post, in the order posts first recorded a stage.
+static TIMINGS: Mutex<Vec<PostTiming>> = Mutex::new(Vec::new());
+
+/// The parts of processing a post that `--profile` tells apart.
+#[derive(Clone, Copy)]
+pub enum Stage {
+    /// Fetching the post and its comments from Reddit.
+    Fetch,
+    /// Every Gemini call for the post, including comment summaries, retries, and top-ups.
+    Gemini,
+    /// Parsing, validating, and de-duplicating the reply.
+    Parse,
+    /// Sheets, Postgres, GitHub, and Trello exports.
+    Export,
+}
+
+const STAGES: [Stage; 4] = [Stage::Fetch, Stage::Gemini, Stage::Parse, Stage::Export];
+
+impl Stage {
+    fn label(self) -> &'static str {
+        match self {
+            Stage::Fetch => "Fetch",
+            Stage::Gemini => "Gemini",
+            Stage::Parse => "Parse",
+            Stage::Export => "Export",
+        }
+    }
+}
+
+struct PostTiming {
+    url: String,
+    stages: [Duration; 4],
+}
+
+/// Start recording stage timings for this run (`--profile`).
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Add `elapsed` to `stage` for the post at `url`.
+pub fn record(url: &str, stage: Stage, elapsed: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut timings) = TIMINGS.lock() {
+        let index = match timings.iter().position(|t| t.url == url) {
+            Some(index) => index,
+            None => {
+                timings.push(PostTiming {
+                    url: url.to_string(),
+                    stages: [Duration::ZERO; 4],
+                });
+                timings.len() - 1
+            }
+        };
+        timings[index].stages[stage as usize] += elapsed;
+    }
+}
+
+/// Await `work`, counting the time toward `stage` for `url`.
+pub async fn timed<T>(url: &str, stage: Stage, work: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let out = work.await;
+    record(url, stage, start.elapsed());
+    out
+}
+
+/// Run `work`, counting the time toward `stage` for `url`.
+pub fn measure<T>(url: &str, stage: Stage, work: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let out = work();
+    record(url, stage, start.elapsed());
+    out
+}
+
+/// Per-post and total seconds by stage, as a table. `None` unless profiling recorded something.
+pub fn report() -> Option<String> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let timings = TIMINGS.lock().ok()?;
+    if timings.is_empty() {
+        return None;
+    }
+
+    let url_width = timings.iter().map(|t| t.url.chars().count()).max().unwrap_or(0).max(5);
+    let mut out = format!("{:<width$}", "Post", width = url_width);
+    for stage in STAGES {
+        out.push_str(&format!("  {:>7}", stage.label()));
+    }
+    out.push_str(&format!("  {:>7}\n", "Total"));
+
+    let mut totals = [Duration::ZERO; 4];
+    for timing in timings.iter() {
+        out.push_str(&row(&timing.url, &timing.stages, url_width));
+        for (total, stage) in totals.iter_mut().zip(timing.stages) {
+            *total += stage;
+        }
+    }
+    out.push_str(&row("Total", &totals, url_width));
+    Some(out)
+}
+
+fn row(label: &str, stages: &[Duration; 4], url_width: usize) -> String {
+    let mut line = format!("{:<width$}", label, width = url_width);
+    for stage in stages {
+        line.push_str(&format!("  {:>7.2}", stage.as_secs_f64()));
+    }
+    let total: Duration = stages.iter().sum();
+    line.push_str(&format!("  {:>7.2}\n", total.as_secs_f64()));
+    line
+}
@@ -0,0 +1,36 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A seed from the clock, for runs that don't pass `--seed`.
+pub fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Pick `n` items uniformly at random, keeping their original order.
+/// The same `seed` always picks the same items from the same input.
+pub fn sample<T: Clone>(items: &[T], n: usize, seed: u64) -> Vec<T> {
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    let n = n.min(items.len());
+    let mut state = seed;
+
+    // Partial Fisher-Yates: the first n slots end up holding a uniform sample
+    for i in 0..n {
+        let j = i + (splitmix64(&mut state) % (items.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+
+    let mut chosen = indices[..n].to_vec();
+    chosen.sort_unstable();
+    chosen.into_iter().map(|i| items[i].clone()).collect()
+}
+
+/// SplitMix64: small, fast, and good enough for picking posts.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// Run-wide cap on in-flight Reddit and Gemini requests. Unset means no cap.
+static REQUEST_SLOTS: OnceLock<Semaphore> = OnceLock::new();
+
+/// How long every Reddit request waits after a 429, in seconds. 0 turns the cooldown off.
+static REDDIT_COOLDOWN_SECS: AtomicU64 = AtomicU64::new(30);
+
+/// When Reddit requests may resume after the last 429. Shared so the whole scan backs off at once.
+static REDDIT_PAUSED_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Allow at most `max` outbound requests at once across all services (`--max-concurrent-requests`).
+pub fn set_max_concurrent_requests(max: usize) {
+    let _ = REQUEST_SLOTS.set(Semaphore::new(max.max(1)));
+}
+
+/// Set how long Reddit requests pause after a 429 (`--rate-limit-cooldown`).
+pub fn set_reddit_cooldown(secs: u64) {
+    REDDIT_COOLDOWN_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Pause all Reddit requests after a 429, for the cooldown or Reddit's `Retry-After`, whichever
+/// is longer. A 429 that arrives during a pause only extends it, so the pause is logged once.
+pub fn reddit_rate_limited(retry_after: Option<Duration>) {
+    let cooldown = Duration::from_secs(REDDIT_COOLDOWN_SECS.load(Ordering::Relaxed));
+    if cooldown.is_zero() {
+        return;
+    }
+    let pause = retry_after.map_or(cooldown, |r| r.max(cooldown));
+    let until = Instant::now() + pause;
+    let Ok(mut paused_until) = REDDIT_PAUSED_UNTIL.lock() else {
+        return;
+    };
+    let already_paused = paused_until.is_some_and(|p| p > Instant::now());
+    if paused_until.is_none_or(|p| until > p) {
+        *paused_until = Some(until);
+    }
+    if !already_paused {
+        eprintln!(
+            "🧊 Reddit is rate limiting; pausing all Reddit requests for {}s",
+            pause.as_secs()
+        );
+    }
+}
+
+/// Wait out any Reddit cooldown, then for a request slot. Hold the permit as with [`request_slot`].
+pub async fn reddit_slot() -> Option<SemaphorePermit<'static>> {
+    // Loop, since another 429 may extend the pause while this request sleeps
+    loop {
+        let until = REDDIT_PAUSED_UNTIL.lock().ok().and_then(|p| *p);
+        match until {
+            Some(until) if until > Instant::now() => tokio::time::sleep_until(until).await,
+            _ => break,
+        }
+    }
+    request_slot().await
+}
+
+/// Wait for a request slot. Hold the returned permit until the response body has been read.
+pub async fn request_slot() -> Option<SemaphorePermit<'static>> {
+    match REQUEST_SLOTS.get() {
+        Some(slots) => slots.acquire().await.ok(),
+        None => None,
+    }
+}
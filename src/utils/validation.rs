@@ -21,6 +21,13 @@ pub fn validate_reddit_url(url: &str) -> Result<String, AppError> {
 
     // Must be a post URL (contains /comments/)
     if !trimmed.contains("/comments/") {
+        if lower.contains("/r/") {
+            return Err(AppError::InvalidInput(format!(
+                "{} is a subreddit or listing URL, not a post. Use `subreddit <name>` to analyze \
+                 its hot posts.",
+                trimmed
+            )));
+        }
         return Err(AppError::InvalidInput(format!(
             "URL must be a Reddit post (must contain /comments/): {}",
             trimmed
@@ -35,3 +42,122 @@ pub fn validate_reddit_url(url: &str) -> Result<String, AppError> {
 
     Ok(clean)
 }
+
+/// Validates a subreddit name before it goes into a URL. Accepts the forms people
+/// paste: `startups`, `r/startups`, `/r/startups/`, a subreddit URL, or `u/name` for a
+/// user's profile (Reddit serves it as the subreddit `u_name`).
+/// Returns the bare name, e.g. `startups` for `https://www.reddit.com/r/startups/hot`.
+pub fn validate_subreddit_name(name: &str) -> Result<String, AppError> {
+    let trimmed = name.trim();
+    let bare = normalize_subreddit(trimmed);
+    let bare = bare.as_str();
+
+    if bare.is_empty() {
+        return Err(AppError::InvalidInput("Subreddit name cannot be empty".into()));
+    }
+
+    // Reddit allows 3-21 letters, digits, and underscores; a few early subreddits
+    // such as r/de and r/nl have two-letter names, so two is accepted too. Profile
+    // subreddits are `u_` plus a username of up to 20 characters.
+    let max = if bare.starts_with("u_") { 22 } else { 21 };
+    let len = bare.chars().count();
+    let allowed = bare.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !allowed || !(2..=max).contains(&len) {
+        return Err(AppError::InvalidInput(format!(
+            "'{}' is not a valid subreddit name (use 2-{} letters, digits, or underscores)",
+            trimmed, max
+        )));
+    }
+
+    Ok(bare.to_string())
+}
+
+/// Strip URL, `/r/`, and `r/` prefixes, and turn a `u/` or `/user/` profile into `u_name`.
+/// A bare name is returned as given, so a typo like `start/ups` still fails validation.
+fn normalize_subreddit(input: &str) -> String {
+    let mut rest = input;
+    // "https://old.reddit.com/r/x/hot" -> "r/x/hot"
+    if let Some((_, path)) = rest.split_once("reddit.com/") {
+        rest = path;
+    }
+    let rest = rest.trim_start_matches('/');
+
+    let (profile, rest) = if let Some(name) = rest.strip_prefix("r/") {
+        (false, name)
+    } else if let Some(name) = rest.strip_prefix("u/").or_else(|| rest.strip_prefix("user/")) {
+        (true, name)
+    } else {
+        return rest.to_string();
+    };
+
+    // Drop anything after the name, such as "/hot" or a trailing slash
+    let name = rest.split(['/', '?']).next().unwrap_or("");
+    if profile {
+        format!("u_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subreddit(input: &str) -> Option<String> {
+        validate_subreddit_name(input).ok()
+    }
+
+    #[test]
+    fn accepts_bare_name() {
+        assert_eq!(subreddit("startups").as_deref(), Some("startups"));
+        assert_eq!(subreddit("  SaaS ").as_deref(), Some("SaaS"));
+    }
+
+    #[test]
+    fn strips_r_prefix() {
+        assert_eq!(subreddit("r/startups").as_deref(), Some("startups"));
+        assert_eq!(subreddit("/r/startups/").as_deref(), Some("startups"));
+        assert_eq!(subreddit("/r/startups/hot").as_deref(), Some("startups"));
+    }
+
+    #[test]
+    fn strips_full_url() {
+        assert_eq!(
+            subreddit("https://www.reddit.com/r/startups/hot").as_deref(),
+            Some("startups")
+        );
+        assert_eq!(
+            subreddit("https://old.reddit.com/r/startups/?sort=new").as_deref(),
+            Some("startups")
+        );
+    }
+
+    #[test]
+    fn maps_profiles_to_user_subreddits() {
+        assert_eq!(subreddit("u/spez").as_deref(), Some("u_spez"));
+        assert_eq!(subreddit("/user/spez/").as_deref(), Some("u_spez"));
+        assert_eq!(
+            subreddit("https://www.reddit.com/user/spez").as_deref(),
+            Some("u_spez")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert_eq!(subreddit(""), None);
+        assert_eq!(subreddit("r/"), None);
+        assert_eq!(subreddit("start/ups"), None);
+        assert_eq!(subreddit("startups/"), None);
+        assert_eq!(subreddit("start ups"), None);
+        assert_eq!(subreddit("a"), None);
+        assert_eq!(subreddit("abcdefghijklmnopqrstuv"), None);
+    }
+
+    #[test]
+    fn length_limits_match_the_error_message() {
+        assert_eq!(subreddit("de").as_deref(), Some("de"));
+        assert_eq!(subreddit("abcdefghijklmnopqrstu").as_deref(), Some("abcdefghijklmnopqrstu"));
+        let err = validate_subreddit_name("a").unwrap_err().to_string();
+        assert!(err.contains("use 2-21 letters"), "{}", err);
+    }
+}
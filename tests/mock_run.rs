@@ -0,0 +1,118 @@
+//! End-to-end runs of the binary in `--mock` mode, answered from `fixtures/`.
+
+use std::process::{Command, Output};
+
+const POST_URL: &str = "https://www.reddit.com/r/freelance/comments/mock01/x";
+const FIXTURE_IDEAS: [&str; 3] = ["PayChase", "RefMatch", "Friday Close"];
+
+fn run(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_reddit-research-engine"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("MOCK_FIXTURES_DIR", concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"))
+        .args(["--mock", "-q"])
+        .args(args)
+        .output()
+        .expect("failed to run the binary");
+    assert!(
+        output.status.success(),
+        "exit {:?}: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn stdout_json(args: &[&str]) -> serde_json::Value {
+    let output = run(args);
+    serde_json::from_slice(&output.stdout).expect("stdout is not JSON")
+}
+
+fn product_names(result: &serde_json::Value) -> Vec<&str> {
+    result["ideas"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|idea| idea["product_name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn analyze_emits_fixture_ideas_as_json() {
+    let run = stdout_json(&["analyze", "--format", "json", POST_URL]);
+    let results = run["results"].as_array().unwrap();
+
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result["subreddit"], "freelance");
+    assert_eq!(result["url"], POST_URL);
+    assert_eq!(result["title"], "How do you keep track of unpaid invoices across clients?");
+    assert_eq!(product_names(result), FIXTURE_IDEAS);
+    assert_eq!(result["ideas"][0]["category"], "fintech");
+    assert_eq!(result["ideas"][0]["effort_estimate"], "1-2 weeks");
+}
+
+#[test]
+fn subreddit_analyzes_every_listed_post() {
+    let run = stdout_json(&["subreddit", "freelance", "--format", "json"]);
+    let results = run["results"].as_array().unwrap();
+
+    let urls: Vec<&str> = results.iter().map(|r| r["url"].as_str().unwrap()).collect();
+    assert_eq!(urls.len(), 3);
+    for (url, id) in urls.iter().zip(["mock01", "mock02", "mock03"]) {
+        assert!(url.contains(&format!("/r/freelance/comments/{}/", id)), "{}", url);
+    }
+    for result in results {
+        assert_eq!(result["subreddit"], "freelance");
+        assert_eq!(product_names(result), FIXTURE_IDEAS);
+    }
+}
+
+#[test]
+fn analyze_emits_one_csv_row_per_idea() {
+    let output = run(&["analyze", "--format", "csv", POST_URL]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.trim_end().lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "Subreddit,Post URL,Post Title,Product Name,Target User,Core Problem,MVP Features,\
+         Monetization,Feasibility,Idea ID,Source Evidence,Effort Estimate,Category,\
+         Novelty Score,Feasibility Score,Market Fit Score"
+    );
+    assert_eq!(lines.len(), 1 + FIXTURE_IDEAS.len());
+    for (line, name) in lines[1..].iter().zip(FIXTURE_IDEAS) {
+        let prefix = format!("freelance,{},How do you keep track of unpaid invoices across clients?,{},", POST_URL, name);
+        assert!(line.starts_with(&prefix), "{}", line);
+    }
+}
+
+/// A fresh, empty directory for one test's saved files.
+fn save_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("mock-run-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn file_names(dir: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn subreddit_placeholder_names_each_sidecar_after_its_file() {
+    let dir = save_dir("sidecar");
+    let template = dir.join("out-{subreddit}.json");
+    run(&["subreddit", "freelance", "--format", "json", "--save", template.to_str().unwrap()]);
+
+    assert_eq!(file_names(&dir), ["out-freelance.json", "out-freelance.meta.json"]);
+    let meta: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("out-freelance.meta.json")).unwrap()).unwrap();
+    assert_eq!(meta["posts"], 3);
+    assert_eq!(meta["ideas"], 9);
+    let _ = std::fs::remove_dir_all(&dir);
+}